@@ -0,0 +1,95 @@
+use game::{
+    heatmap::{
+        blend,
+        encode_png,
+        heat,
+        render_heights,
+        shade_layout,
+        HeatmapLayer,
+        TopDownView,
+        IMAGE_SIZE
+    },
+    map::{
+        load_map_triangles,
+        map_path,
+        map_transform
+    },
+    telemetry::load_runs
+};
+use std::{
+    env,
+    fs,
+    path::PathBuf,
+    process::ExitCode
+};
+
+// Draws heatmaps of the shot logs over the map, run with
+// `cargo run --bin heatmap -- [telemetry directory] [output directory] [--level <n>]`
+fn main() -> ExitCode {
+    let mut directories = Vec::new();
+    let mut level = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--level" {
+            match args.next().and_then(|number| number.parse::<u8>().ok()) {
+                Some(number) => level = Some(number),
+                None => {
+                    eprintln!("--level needs a level number");
+                    return ExitCode::FAILURE;
+                }
+            }
+        } else {
+            directories.push(PathBuf::from(arg));
+        }
+    }
+    let telemetry_directory = directories.first().cloned().unwrap_or_else(|| PathBuf::from("telemetry"));
+    let output_directory = directories.get(1).cloned().unwrap_or_else(|| PathBuf::from("heatmaps"));
+
+    let runs = match load_runs(&telemetry_directory) {
+        Ok(runs) => runs,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let runs: Vec<_> = runs.into_iter().filter(|run| level.map_or(true, |level| run.level == level)).collect();
+    if runs.is_empty() {
+        eprintln!("No runs to draw in {}", telemetry_directory.display());
+        return ExitCode::FAILURE;
+    }
+
+    // the map is drawn the way it is turned in game, where the logged positions are
+    let (vertices, triangles) = match load_map_triangles(&map_path()) {
+        Ok(map) => map,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let transform = map_transform();
+    let vertices: Vec<_> = vertices.iter().map(|vertex| transform.transform_point(*vertex)).collect();
+    let view = TopDownView::fit(&vertices, IMAGE_SIZE);
+    let layout = shade_layout(&view, &render_heights(&view, &vertices, &triangles));
+
+    if let Err(error) = fs::create_dir_all(&output_directory) {
+        eprintln!("Could not create {}: {}", output_directory.display(), error);
+        return ExitCode::FAILURE;
+    }
+    let suffix = level.map_or(String::new(), |level| format!("-level{}", level));
+    for layer in HeatmapLayer::ALL {
+        let points: Vec<_> = runs.iter().flat_map(|run| layer.points(run)).collect();
+        let image = blend(&layout, &heat(&view, &points));
+        let path = output_directory.join(format!("{}{}.png", layer.name(), suffix));
+        let written = encode_png(view.size, &image)
+            .and_then(|bytes| fs::write(&path, bytes).map_err(|error| format!("Could not write {}: {}", path.display(), error)));
+        match written {
+            Ok(()) => println!("{} {} from {} runs in {}", points.len(), layer.name(), runs.len(), path.display()),
+            Err(error) => {
+                eprintln!("{}", error);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,56 @@
+use game::{
+    navigation::{
+        MapShape,
+        NavGrid
+    },
+    structs::{
+        LevelController,
+        PlayerController,
+        TimeController
+    },
+    validation::validate_level
+};
+use std::process::ExitCode;
+
+// Checks every level against the map collision, run with `cargo run --bin validate-levels`
+fn main() -> ExitCode {
+    let map = match MapShape::load() {
+        Ok(map) => map,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let player_controller = PlayerController::default();
+    let grid = NavGrid::build(&map, player_controller.spawn_point);
+    let levels = LevelController::default();
+    let times = TimeController::default();
+
+    let all_levels = [
+        (&levels.level_1_pos, times.level_1_time),
+        (&levels.level_2_pos, times.level_2_time),
+        (&levels.level_3_pos, times.level_3_time),
+        (&levels.level_4_pos, times.level_4_time),
+        (&levels.level_5_pos, times.level_5_time)
+    ];
+
+    let mut problem_count = 0;
+    for (index, (positions, time_limit)) in all_levels.iter().enumerate() {
+        let report = validate_level(&map, &grid, player_controller.spawn_point, player_controller.speed, positions, *time_limit);
+
+        let route = report.route_time.map_or("no route".to_string(), |time| format!("route about {:.0}s", time));
+        println!("Level {}: {} targets, {:.0}s limit, {}", index + 1, positions.len(), time_limit, route);
+        for problem in &report.problems {
+            println!("    {}", problem);
+        }
+        problem_count += report.problems.len();
+    }
+
+    if problem_count > 0 {
+        println!("{} problems found", problem_count);
+        return ExitCode::FAILURE;
+    }
+    println!("All levels look fine");
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,180 @@
+use bevy::{
+    input::InputPlugin,
+    prelude::*,
+    scene::ScenePlugin,
+    time::TimeUpdateStrategy,
+    window::ExitCondition
+};
+use bevy_rapier3d::prelude::*;
+use crate::{
+    GameState,
+    GameplayPlugin,
+    GunplayPlugin,
+    MenuPlugin,
+    ReplayPlugin,
+    ViewerPlugin,
+    entities::player_body,
+    game::MAX_VERTICAL_ANGLE,
+    map::{
+        load_map_collider,
+        map_path,
+        map_transform
+    },
+    settings::viewmodel_position,
+    structs::{
+        CameraController,
+        GameEntity,
+        GunController,
+        MapCollider,
+        PlayerController,
+        PlayerEntity,
+        Settings
+    }
+};
+use std::time::Duration;
+
+// Every update of the headless app moves time forward by exactly one fixed tick
+pub use crate::replay::TICK;
+
+// Builds the game without a window or gpu, time only moves when the app is updated
+pub fn headless_app() -> App {
+    let mut app = App::new();
+
+    app
+    .add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        // only registers the window events, no window is opened
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false
+        },
+        AssetPlugin::default(),
+        // rapier looks for the scene spawner even though no scenes are spawned
+        ScenePlugin
+    ))
+    // the game still creates meshes, materials and text, they are just never drawn
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .init_asset::<Image>()
+    .init_asset::<Font>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(TICK)))
+    .add_plugins((
+        GameplayPlugin,
+        GunplayPlugin,
+        MenuPlugin,
+        ReplayPlugin,
+        ViewerPlugin,
+        HeadlessPlugin
+    ));
+
+    app
+}
+
+// Spawns the map collision and a player without any models in place of the rendering plugin
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let collider = match load_map_collider(&map_path()) {
+            Ok(collider) => Some(collider),
+            Err(error) => {
+                warn!("{}", error);
+                None
+            }
+        };
+
+        app
+        .insert_resource(MapCollider { collider })
+        .add_systems(OnTransition {
+            from: GameState::MainMenu,
+            to: GameState::Start
+        }, setup)
+        .add_systems(OnTransition {
+            from: GameState::MainMenu,
+            to: GameState::Replay
+        }, setup);
+    }
+}
+
+// Sets up the map and the player, like entities::setup without anything visible
+pub fn setup(
+    mut commands: Commands,
+    map_collider: Res<MapCollider>,
+    player_controller: Res<PlayerController>,
+    mut player_entity: ResMut<PlayerEntity>,
+    settings: Res<Settings>
+) {
+    // map
+    if let Some(collider) = &map_collider.collider {
+        commands.spawn((
+            TransformBundle::from(map_transform()),
+            RigidBody::Fixed,
+            collider.clone(),
+            GameEntity
+        ));
+    }
+
+    // player
+    let player_entity_id = commands.spawn((
+        PlayerController { ..Default::default() },
+        player_body(&player_controller),
+        GameEntity
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            TransformBundle::from(Transform::from_translation(Vec3::new(0.0, 0.650, 0.0))),
+            Camera::default(),
+            CameraController::default()
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TransformBundle::from(Transform::from_translation(viewmodel_position(&player_controller, &settings))),
+                GunController::default()
+            ));
+        });
+    })
+    .id();
+
+    player_entity.entity = Some(player_entity_id);
+}
+
+// Turns the player and camera to look at a point, the shot only goes there after the next
+// update has moved the camera
+pub fn look_at(world: &mut World, point: Vec3) {
+    let mut camera_query = world.query_filtered::<&GlobalTransform, With<CameraController>>();
+    let Some(camera_transform) = camera_query.iter(world).next() else { return };
+
+    if let Some((yaw, pitch)) = view_angles(camera_transform.translation(), point) {
+        set_view(world, yaw, pitch);
+    }
+}
+
+// The yaw and pitch that look from one point at another
+pub fn view_angles(from: Vec3, to: Vec3) -> Option<(f32, f32)> {
+    let direction = (to - from).normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+    Some(((-direction.x).atan2(-direction.z), direction.y.asin()))
+}
+
+// Sets the camera yaw and pitch, the same way mouse_callback turns the player and camera
+pub fn set_view(world: &mut World, yaw: f32, pitch: f32) {
+    let pitch = pitch.clamp(-MAX_VERTICAL_ANGLE, MAX_VERTICAL_ANGLE);
+
+    let mut camera_query = world.query_filtered::<(&mut CameraController, &mut Transform), Without<PlayerController>>();
+    for (mut camera, mut transform) in camera_query.iter_mut(world) {
+        camera.yaw = yaw;
+        camera.pitch = pitch;
+        transform.rotation = Quat::from_axis_angle(Vec3::X, pitch);
+    }
+
+    let mut player_query = world.query_filtered::<&mut Transform, With<PlayerController>>();
+    for mut player_transform in player_query.iter_mut(world) {
+        player_transform.rotation = Quat::from_axis_angle(Vec3::Y, yaw);
+    }
+}
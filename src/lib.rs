@@ -0,0 +1,78 @@
+pub mod modules;
+pub mod rendering;
+pub mod plugins;
+pub mod headless;
+
+use bevy::prelude::*;
+
+pub use modules::{
+    game,
+    controls,
+    audio,
+    gunplay,
+    structs,
+    menu,
+    input,
+    gamepad,
+    settings,
+    crosshair,
+    effects,
+    viewmodel,
+    map,
+    navigation,
+    validation,
+    replay,
+    viewer,
+    telemetry,
+    heatmap
+};
+pub use rendering::{
+    lighting,
+    entities
+};
+pub use plugins::{
+    MenuPlugin,
+    GameplayPlugin,
+    GunplayPlugin,
+    AudioPlugin,
+    RenderingPlugin,
+    ReplayPlugin,
+    ViewerPlugin
+};
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, States, Resource, Default, Copy)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    PauseMenu,
+    Start,
+    Playing,
+    Failed,
+    Won,
+    // watching a replay, nothing is simulated
+    Replay
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, States, Default)]
+pub enum MenuState {
+    #[default]
+    Root,
+    Settings,
+    Controls,
+    Sensitivity,
+    Crosshair,
+    Audio,
+    Weapon
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, States, Resource, Default)]
+pub enum LevelState {
+    NoLevel,
+    #[default]
+    Level1,
+    Level2,
+    Level3,
+    Level4,
+    Level5,
+    Failed
+}
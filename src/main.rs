@@ -1,83 +1,85 @@
-mod modules;
-mod rendering;
-
-use crate::{
-    //controls::Positions,
-    structs::{
-        PlayerController,
-        MapController,
-        GunController,
-        AudioController,
-        CubemapController,
-        LevelController,
-        EntityHandler,
-        TargetController,
-        TimeController,
-        LastState,
-        PlayerEntity
-    }
-};
-// use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use bevy_scene_hook::HookPlugin;
-use bevy_rapier3d::prelude::*;
 use bevy::{
-    diagnostic::FrameTimeDiagnosticsPlugin,
     prelude::*,
     window::{
         Cursor,
         CursorGrabMode,
-        PresentMode, 
-        WindowMode,
         WindowResolution,
         WindowTheme
     }
 };
-use modules::{
-    game, 
-    controls,
-    audio,
-    gunplay,
-    structs,
-    menu
+use game::{
+    input,
+    replay,
+    settings,
+    structs::{
+        ConfigDirectory,
+        ReplayPlayback,
+        ReplayRecorder,
+        ReplayViewer,
+        TelemetryRecorder
+    },
+    MenuPlugin,
+    GameplayPlugin,
+    GunplayPlugin,
+    AudioPlugin,
+    RenderingPlugin,
+    ReplayPlugin,
+    ViewerPlugin
 };
-use rendering::{
-    lighting,
-    entities
+use std::{
+    env,
+    path::PathBuf
 };
+// use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, States, Resource, Default, Copy)]
-pub enum GameState {
-    #[default]
-    MainMenu,
-    PauseMenu,
-    Start,
-    Playing,
-    Failed,
-    Won
-}
+fn main() {
+    let config = ConfigDirectory(Some(PathBuf::from("config")));
+    let mut settings = settings::load_settings(&config);
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, States, Resource, Default)]
-pub enum LevelState {
-    NoLevel,
-    #[default]
-    Level1,
-    Level2,
-    Level3,
-    Level4,
-    Level5,
-    Failed
-}
+    // `--replay <file>` plays a replay back instead of recording new ones,
+    // `--view <file>` opens it in the replay viewer
+    let args: Vec<String> = env::args().collect();
+    let mut playback = None;
+    let mut viewer = None;
+    for flag in ["--replay", "--view"] {
+        let Some(index) = args.iter().position(|arg| arg == flag) else { continue };
+        let Some(path) = args.get(index + 1) else {
+            eprintln!("{} needs a replay file", flag);
+            return;
+        };
+        let replay = match replay::load_replay(&PathBuf::from(path)) {
+            Ok(replay) => replay,
+            Err(error) => {
+                eprintln!("{}", error);
+                return;
+            }
+        };
+        settings = replay::playback_settings(&settings, &replay.settings);
+        if flag == "--replay" {
+            playback = Some(ReplayPlayback::new(replay));
+        } else {
+            viewer = Some(ReplayViewer::new(replay));
+        }
+    }
+    let recorder = ReplayRecorder {
+        directory: (playback.is_none() && viewer.is_none()).then(|| PathBuf::from("replays")),
+        ..Default::default()
+    };
+    let telemetry = TelemetryRecorder {
+        directory: (playback.is_none() && viewer.is_none()).then(|| PathBuf::from("telemetry")),
+        ..Default::default()
+    };
 
-fn main() {
-    App::new()
+    let mut app = App::new();
+    app
     .add_plugins((
         DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "VALORANT 2.0".into(),
                 //resolution: (800., 600.).into(),
-                mode: WindowMode::BorderlessFullscreen,
-                resolution: WindowResolution::new(1920., 1080.),
-                present_mode: PresentMode::AutoVsync,
+                mode: settings.window_mode,
+                resolution: WindowResolution::new(settings.resolution.0 as f32, settings.resolution.1 as f32),
+                present_mode: settings::present_mode(&settings),
                 window_theme: Some(WindowTheme::Dark),
                 cursor: Cursor { 
                     icon: default(),
@@ -96,112 +98,33 @@ fn main() {
             }),
             exit_condition: bevy::window::ExitCondition::OnPrimaryClosed,
             close_when_requested: true,
-            ..default()
         }),
         //LogDiagnosticsPlugin::default(),
-        FrameTimeDiagnosticsPlugin,
-        RapierPhysicsPlugin::<NoUserData>::default(),
         //RapierDebugRenderPlugin::default(),
-        HookPlugin,
         //WorldInspectorPlugin::new(),
     ))
-    .insert_state(GameState::MainMenu)
-    .insert_state(LevelState::Level1)
-    .insert_resource(Msaa::Sample8)
-    .init_resource::<PlayerController>() 
-    .init_resource::<TargetController>()
-    .init_resource::<TimeController>()
-    .init_resource::<MapController>()
-    .init_resource::<GunController>()  
-    .init_resource::<AudioController>()
-    .init_resource::<CubemapController>()
-    .init_resource::<LevelController>()
-    .init_resource::<EntityHandler>()
-    .init_resource::<LevelState>()
-    .init_resource::<LastState>()
-    .init_resource::<PlayerEntity>()
-    //.init_resource::<Positions>()
-    // main menu
-    .add_systems(OnEnter(GameState::MainMenu), menu::setup_main_menu)
-    .add_systems(Update, menu::menu_interactions.run_if(game::in_main_menu_state))
-    // pause menu
-    .add_systems(OnEnter(GameState::PauseMenu), menu::setup_pause_menu)
-    .add_systems(Update, menu::menu_interactions.run_if(game::in_pause_menu_state))
-    // game
-    .add_systems(OnTransition {
-        from: GameState::MainMenu, 
-        to: GameState::Start
-    }, (
-        game::setup,
-        entities::setup,
-        lighting::setup,
+    .insert_resource(settings::msaa_from_samples(settings.msaa_samples))
+    .insert_resource(settings)
+    .insert_resource(input::load_bindings(&config))
+    .add_plugins((
+        GameplayPlugin,
+        GunplayPlugin,
+        AudioPlugin,
+        RenderingPlugin,
+        MenuPlugin,
+        ReplayPlugin,
+        ViewerPlugin
     ))
-    .add_systems(OnEnter(LevelState::Failed), entities::despawn_targets)
-    .add_systems(OnEnter(LevelState::NoLevel), entities::despawn_targets)
-    .add_systems(OnEnter(LevelState::Level1), game::initiate_level)
-    .add_systems(OnEnter(LevelState::Level2), game::initiate_level)
-    .add_systems(OnEnter(LevelState::Level3), game::initiate_level)
-    .add_systems(OnEnter(LevelState::Level4), game::initiate_level)
-    .add_systems(OnEnter(LevelState::Level5), game::initiate_level)
-    // start
-    .add_systems(Update, (
-        game::update,
-        game::mouse_callback,
-        game::diagnostics,
-        entities::rotate_map,
-        entities::rotate_gun,
-        entities::load_cubemap,
-        //controls::update,
-        gunplay::update,
-        audio::audio_playback,
-        audio::audio_control
-    ).run_if(game::in_start_state))
-    // playing
-    .add_systems(Update, (
-        game::update,
-        game::change_level_state,
-        game::update_level_timer,
-        game::mouse_callback,
-        game::diagnostics,
-        entities::rotate_map,
-        entities::rotate_gun,
-        entities::load_cubemap,
-        controls::update,
-        gunplay::update,
-        audio::audio_playback,
-    ).run_if(game::in_playing_state))
-    // won
-    .add_systems(Update, (
-        game::update,
-        game::mouse_callback,
-        game::diagnostics,
-        controls::update
-    ).run_if(game::in_won_state))
-    // text systems
-    .add_systems(OnEnter(GameState::Start), entities::spawn_start_text)
-    .add_systems(OnEnter(LevelState::Failed), entities::spawn_fail_text)
-    .add_systems(OnEnter(GameState::Won), entities::spawn_win_text)
-    // cleanup systems
-    .add_systems(OnExit(GameState::MainMenu), entities::despawn_menu_entities)
-    .add_systems(OnExit(GameState::PauseMenu), entities::despawn_menu_entities)
-    .add_systems(OnEnter(GameState::Playing), entities::despawn_text_entities)
-    .add_systems(OnTransition {
-        from: GameState::PauseMenu,
-        to: GameState::MainMenu
-    }, entities::despawn_game_entities)
-    // misc
-    .add_systems(Startup, (entities::load_entities, audio::load_audio, audio::audio_control))
-    .add_systems(OnTransition {
-        from: GameState::PauseMenu,
-        to: GameState::Playing
-    }, game::change_cursor_state)
-    .add_systems(OnTransition {
-        from: GameState::PauseMenu,
-        to: GameState::Start
-    }, game::change_cursor_state)
-    .add_systems(OnTransition {
-        from: GameState::PauseMenu,
-        to: GameState::Won
-    }, game::change_cursor_state)
-    .run();
+    .insert_resource(config)
+    .insert_resource(recorder)
+    .insert_resource(telemetry);
+
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+    if let Some(viewer) = viewer {
+        app.insert_resource(viewer);
+    }
+
+    app.run();
 }
\ No newline at end of file
@@ -1,70 +1,368 @@
 use bevy::{
-    audio::prelude::AudioSink,
-    prelude::*
+    audio::{
+        prelude::{
+            AudioSink,
+            SpatialAudioSink
+        },
+        Decodable,
+        SpatialScale,
+        Volume
+    },
+    prelude::*,
+    window::WindowFocused
 };
-use crate::structs::{
-    AudioController,
-    GunController,
-    Ambience,
-    GameEntity
+use crate::{
+    GameState,
+    LevelState,
+    structs::{
+        AudioBus,
+        AudioController,
+        Ambience,
+        GameEntity,
+        LevelController,
+        Settings,
+        ShotFiredEvent,
+        TargetHitEvent,
+        WorldSound,
+        WorldSoundEvent
+    }
 };
+use std::time::Duration;
+
+// Shrinks distances for the spatial mixer so sounds across the map stay audible
+const SPATIAL_SCALE: f32 = 0.1;
+
+// Played in the menus and in levels that don't name a track of their own
+pub const SHARED_AMBIENCE: &str = "de_dust2_ambience.ogg";
 
 // Loads audio assets
 pub fn load_audio(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    mut pitch_assets: ResMut<Assets<Pitch>>
 ) {
-    let ambience = Some(asset_server.load("de_dust2_ambience.ogg"));
+    let ambience = Some(asset_server.load(SHARED_AMBIENCE));
     let gunshot = Some(asset_server.load("ak-47.ogg"));
+    let click = Some(pitch_assets.add(Pitch::new(880.0, Duration::from_millis(40))));
+    let hit = Some(pitch_assets.add(Pitch::new(1320.0, Duration::from_millis(60))));
+    let destroy = Some(pitch_assets.add(Pitch::new(330.0, Duration::from_millis(180))));
+    let footstep = Some(pitch_assets.add(Pitch::new(110.0, Duration::from_millis(50))));
 
     commands.insert_resource(AudioController {
         ambience_handle: ambience,
-        gunshot_handle: gunshot
+        gunshot_handle: gunshot,
+        click_handle: click,
+        hit_handle: hit,
+        destroy_handle: destroy,
+        footstep_handle: footstep,
+        ..Default::default()
     });
 }
 
-// Plays audio on condition
+// Plays a gunshot for every shot fired, a frame can hold more than one tick of shooting
 pub fn audio_playback(
     mut commands: Commands,
-    gun_query: Query<&GunController>,
-    ambience_query: Query<Entity, With<Ambience>>,
-    audio_controller: Res<AudioController>
+    mut shot_events: EventReader<ShotFiredEvent>,
+    audio_controller: Res<AudioController>,
+    settings: Res<Settings>
+) {
+    let weapon_volume = bus_volume(&settings, &audio_controller, AudioBus::Weapon);
+
+    for _ in shot_events.read() {
+        if let Some(gunshot_handle) = &audio_controller.gunshot_handle {
+            commands.spawn((
+                AudioBundle {
+                    source: gunshot_handle.clone(),
+                    settings: PlaybackSettings::REMOVE.with_volume(Volume::new(weapon_volume))
+                },
+                AudioBus::Weapon,
+                GameEntity,
+            ));
+        }
+    }
+}
+
+// Plays a sound at a position in the world, panned and attenuated relative to the camera
+pub fn spawn_spatial_sound<T: Asset + Decodable>(
+    commands: &mut Commands,
+    source: Handle<T>,
+    position: Vec3,
+    volume: f32,
+    bus: AudioBus
+) {
+    commands.spawn((
+        AudioSourceBundle {
+            source,
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(volume))
+                .with_spatial(true)
+                .with_spatial_scale(SpatialScale::new(SPATIAL_SCALE))
+        },
+        SpatialBundle::from_transform(Transform::from_translation(position)),
+        bus,
+        GameEntity
+    ));
+}
+
+// Plays the hit or kill sound where a target was hit
+pub fn play_hit_sounds(
+    mut commands: Commands,
+    mut hit_events: EventReader<TargetHitEvent>,
+    audio_controller: Res<AudioController>,
+    settings: Res<Settings>
+) {
+    for hit_event in hit_events.read() {
+        let sound_handle = if hit_event.killed {
+            &audio_controller.destroy_handle
+        } else {
+            &audio_controller.hit_handle
+        };
+        if let Some(sound_handle) = sound_handle {
+            spawn_spatial_sound(
+                &mut commands,
+                sound_handle.clone(),
+                hit_event.position,
+                bus_volume(&settings, &audio_controller, AudioBus::Weapon),
+                AudioBus::Weapon
+            );
+        }
+    }
+}
+
+// Plays gunfire and footsteps where they happened in the world
+pub fn play_world_sounds(
+    mut commands: Commands,
+    mut sound_events: EventReader<WorldSoundEvent>,
+    audio_controller: Res<AudioController>,
+    settings: Res<Settings>
 ) {
-    for gun_controller in gun_query.iter() {
-        if let Some(ambience_handle) = &audio_controller.ambience_handle {
-            if ambience_query.iter().next().is_none() {
-                // plays ambience if it's not already playing
-                commands.spawn((
-                    AudioBundle {
-                        source: ambience_handle.clone(),
-                        settings: PlaybackSettings::ONCE
-                    },
-                    Ambience,
-                    GameEntity,                    
-                ));
+    let volume = bus_volume(&settings, &audio_controller, AudioBus::Weapon);
+
+    for sound_event in sound_events.read() {
+        match sound_event.sound {
+            WorldSound::Gunshot => if let Some(gunshot_handle) = &audio_controller.gunshot_handle {
+                spawn_spatial_sound(&mut commands, gunshot_handle.clone(), sound_event.position, volume, AudioBus::Weapon);
+            },
+            WorldSound::Footstep => if let Some(footstep_handle) = &audio_controller.footstep_handle {
+                spawn_spatial_sound(&mut commands, footstep_handle.clone(), sound_event.position, volume, AudioBus::Weapon);
             }
         }
+    }
+}
 
-        if let Some(gunshot_handle) = &audio_controller.gunshot_handle {
-            // play gunshot every time i shoot
-            if gun_controller.play_audio {
-                commands.spawn((
-                    AudioBundle {
-                        source: gunshot_handle.clone(),
-                        settings: PlaybackSettings::REMOVE
-                    },
-                    GameEntity,
-                ));
+// Picks the ambience track of the level that was entered
+pub fn select_ambience(
+    asset_server: Res<AssetServer>,
+    levels: Res<LevelController>,
+    current_level: Res<State<LevelState>>,
+    mut audio_controller: ResMut<AudioController>
+) {
+    let track = levels.ambience_track(current_level.get()).unwrap_or(SHARED_AMBIENCE);
+    audio_controller.ambience_handle = Some(asset_server.load(track));
+}
+
+// Loops the ambience by crossfading into a new copy before the track ends, fades over when
+// the track changes and pauses while the pause menu is open
+pub fn update_ambience(
+    mut commands: Commands,
+    mut ambience_query: Query<(Entity, &mut Ambience, Option<&AudioSink>)>,
+    mut audio_controller: ResMut<AudioController>,
+    audio_sources: Res<Assets<AudioSource>>,
+    game_state: Res<State<GameState>>,
+    time: Res<Time>
+) {
+    const CROSSFADE: f32 = 2.0;
+
+    let paused = *game_state.get() == GameState::PauseMenu;
+    let delta_time = time.delta_seconds();
+    let mut playing = false;
+
+    for (entity, mut ambience, sink) in ambience_query.iter_mut() {
+        let Some(sink) = sink else {
+            // still loading
+            playing |= !ambience.fading_out;
+            continue;
+        };
+
+        if paused {
+            sink.pause();
+            playing |= !ambience.fading_out;
+            continue;
+        }
+        if sink.is_paused() {
+            sink.play();
+        }
+
+        let duration = track_duration(&mut audio_controller, &audio_sources, &ambience.handle);
+        let fade_time = duration.map_or(CROSSFADE, |duration| CROSSFADE.min(duration / 4.0));
+        ambience.elapsed += delta_time;
+
+        let track_changed = audio_controller.ambience_handle.as_ref() != Some(&ambience.handle);
+        let near_end = duration.is_some_and(|duration| ambience.elapsed >= duration - fade_time);
+        if !ambience.fading_out && (track_changed || near_end) {
+            ambience.fading_out = true;
+        }
+
+        if ambience.fading_out {
+            ambience.gain -= delta_time / fade_time;
+            if ambience.gain <= 0.0 || sink.empty() {
+                commands.entity(entity).despawn();
             }
+        } else {
+            ambience.gain = (ambience.gain + delta_time / fade_time).min(1.0);
+            playing = true;
         }
     }
+
+    if paused || playing {
+        return;
+    }
+
+    if let Some(ambience_handle) = &audio_controller.ambience_handle {
+        commands.spawn((
+            AudioBundle {
+                source: ambience_handle.clone(),
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(0.0))
+            },
+            Ambience {
+                handle: ambience_handle.clone(),
+                elapsed: 0.0,
+                gain: 0.0,
+                fading_out: false
+            },
+            AudioBus::Ambience
+        ));
+    }
+}
+
+// Returns the length of a track in seconds, read from the ogg headers once it has loaded
+fn track_duration(
+    audio_controller: &mut AudioController,
+    audio_sources: &Assets<AudioSource>,
+    handle: &Handle<AudioSource>
+) -> Option<f32> {
+    if let Some(duration) = audio_controller.ambience_durations.get(&handle.id()) {
+        return Some(*duration);
+    }
+
+    let duration = ogg_duration(&audio_sources.get(handle)?.bytes)?;
+    audio_controller.ambience_durations.insert(handle.id(), duration);
+    Some(duration)
+}
+
+// The length of an ogg vorbis stream without decoding it. The pages are walked by their
+// headers, the sample rate is in the first packet and the granule position of the page that
+// ends the stream is the number of samples
+pub fn ogg_duration(bytes: &[u8]) -> Option<f32> {
+    const END_OF_STREAM: u8 = 0x04;
+
+    let mut sample_rate = None;
+    let mut serial = None;
+    let mut granule_position = None;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        // page header: capture pattern, version, flags, granule position, serial, sequence,
+        // crc and segment count, then the segment table with the length of every segment
+        let header = bytes.get(offset..offset + 27)?;
+        if !header.starts_with(b"OggS") || header[4] != 0 {
+            return None;
+        }
+        let segments = header[26] as usize;
+        let body_start = offset + 27 + segments;
+        let body_length: usize = bytes.get(offset + 27..body_start)?.iter().map(|&length| length as usize).sum();
+        let body = bytes.get(body_start..body_start + body_length)?;
+        let page_serial = u32::from_le_bytes(header[14..18].try_into().ok()?);
+
+        match serial {
+            // the identification packet is alone on the first page
+            None => {
+                if !body.starts_with(b"\x01vorbis") {
+                    return None;
+                }
+                sample_rate = Some(u32::from_le_bytes(body.get(12..16)?.try_into().ok()?));
+                serial = Some(page_serial);
+            }
+            Some(serial) if serial == page_serial && header[5] & END_OF_STREAM != 0 => {
+                granule_position = Some(i64::from_le_bytes(header[6..14].try_into().ok()?));
+            }
+            _ => {}
+        }
+        offset = body_start + body_length;
+    }
+
+    let (sample_rate, granule_position) = (sample_rate?, granule_position?);
+    if sample_rate == 0 || granule_position < 0 {
+        return None;
+    }
+    Some(granule_position as f32 / sample_rate as f32)
 }
 
-// Sets ambience volume to 0.3
-pub fn audio_control(
-    ambience_query: Query<&AudioSink, With<Ambience>>
+// Returns the volume of a bus, scaled by the master volume
+pub fn bus_volume(settings: &Settings, audio_controller: &AudioController, bus: AudioBus) -> f32 {
+    if settings.mute_unfocused && !audio_controller.window_focused {
+        return 0.0;
+    }
+
+    let volume = match bus {
+        AudioBus::Ambience => settings.ambience_volume,
+        AudioBus::Weapon => settings.weapon_volume,
+        AudioBus::Ui => settings.ui_volume
+    };
+    settings.master_volume * volume
+}
+
+// Keeps the volume of every playing sound in line with its bus
+pub fn update_mixer(
+    sink_query: Query<(&AudioSink, &AudioBus, Option<&Ambience>)>,
+    spatial_sink_query: Query<(&SpatialAudioSink, &AudioBus)>,
+    audio_controller: Res<AudioController>,
+    settings: Res<Settings>
 ) {
-    if let Ok(ambience_sink) = ambience_query.get_single() {
-        ambience_sink.set_volume(0.3);
+    for (sink, bus) in spatial_sink_query.iter() {
+        let volume = bus_volume(&settings, &audio_controller, *bus);
+        if sink.volume() != volume {
+            sink.set_volume(volume);
+        }
+    }
+
+    for (sink, bus, ambience) in sink_query.iter() {
+        let gain = ambience.map_or(1.0, |ambience| ambience.gain.clamp(0.0, 1.0));
+        let volume = bus_volume(&settings, &audio_controller, *bus) * gain;
+        if sink.volume() != volume {
+            sink.set_volume(volume);
+        }
     }
-}
\ No newline at end of file
+}
+
+// Tracks whether the window has focus so the game can go quiet in the background
+pub fn track_focus(
+    mut focus_events: EventReader<WindowFocused>,
+    mut audio_controller: ResMut<AudioController>
+) {
+    for focus_event in focus_events.read() {
+        audio_controller.window_focused = focus_event.focused;
+    }
+}
+
+// Plays a click when a menu button is pressed
+pub fn play_ui_sounds(
+    mut commands: Commands,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+    audio_controller: Res<AudioController>,
+    settings: Res<Settings>
+) {
+    let Some(click_handle) = &audio_controller.click_handle else { return };
+
+    for interaction in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            commands.spawn((
+                PitchBundle {
+                    source: click_handle.clone(),
+                    settings: PlaybackSettings::DESPAWN
+                        .with_volume(Volume::new(bus_volume(&settings, &audio_controller, AudioBus::Ui)))
+                },
+                AudioBus::Ui
+            ));
+        }
+    }
+}
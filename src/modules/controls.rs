@@ -6,9 +6,27 @@ use bevy_rapier3d::{
 };
 use crate::structs::{
     PlayerController, 
-    GunController
+    GunController,
+    MovementModel,
+    Settings,
+    InputAction,
+    AnalogInput,
+    CameraController
 };
 
+const STANDING_SEGMENT: f32 = 0.450;
+const CROUCHED_SEGMENT: f32 = 0.130;
+const STANDING_CAMERA: f32 = 0.650;
+const CROUCHED_CAMERA: f32 = 0.300;
+const CAPSULE_RADIUS: f32 = 0.1;
+// The arcade jump lifts jump_height every 60th of a second and loses a tenth of it every frame,
+// source movement does the same jump in meters per second
+const ARCADE_FRAME_RATE: f32 = 60.0;
+// -60 * ln(0.9), fading as fast as the arcade jump does
+const JUMP_DAMPING: f32 = 6.32;
+// How much of the movement has to go forward to sprint, from keys or the stick
+const SPRINT_FORWARD: f32 = 0.5;
+
 //#[derive(Resource)]
 //pub struct Positions(Vec<Vec3>);
 //
@@ -19,14 +37,17 @@ use crate::structs::{
 //}
 
 // Handles player movement
+#[allow(clippy::too_many_arguments)]
 pub fn update(
-    key_event: Res<ButtonInput<KeyCode>>,
-    mut player_query: Query<(&mut Transform, &mut PlayerController), Without<Camera3d>>,
-    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    actions: Res<ButtonInput<InputAction>>,
+    mut player_query: Query<(&mut Transform, &mut PlayerController), Without<CameraController>>,
+    mut camera_query: Query<&mut Transform, With<CameraController>>,
     mut player_collider_query: Query<&mut Collider, With<PlayerController>>,
     mut gun_query: Query<&mut GunController>,
     time: Res<Time>,
     rapier_context: Res<RapierContext>,
+    settings: Res<Settings>,
+    analog_input: Res<AnalogInput>,
     //mut positions: ResMut<Positions>
 ) {
     for (mut transform, mut player) in player_query.iter_mut() {
@@ -62,54 +83,141 @@ pub fn update(
         
                         let mut horizontal_velocity = Vec3::ZERO;
                         
-                        if player.is_crouched {
-                            crouch_modifier = 0.4;
-                        }
-                        if key_event.pressed(KeyCode::KeyW) {
+                        if actions.pressed(InputAction::MoveForward) {
                             horizontal_velocity += forward;
                         }
-                        if key_event.pressed(KeyCode::KeyS) {
+                        if actions.pressed(InputAction::MoveBackward) {
                             horizontal_velocity += backward;
                         }
-                        if key_event.pressed(KeyCode::KeyA) {
+                        if actions.pressed(InputAction::MoveLeft) {
                             horizontal_velocity += left;
                         }
-                        if key_event.pressed(KeyCode::KeyD) {
+                        if actions.pressed(InputAction::MoveRight) {
                             horizontal_velocity += right;
                         }
-                        if key_event.just_pressed(KeyCode::Space) && player.is_grounded {
-                            player.velocity.y = jump_height;
-                        }        
-                        if key_event.pressed(KeyCode::ShiftLeft) {
+                        // the left stick adds its deflection on top of the keys
+                        horizontal_velocity += forward * analog_input.movement.y + right * analog_input.movement.x;
+        
+                        let walking = actions.pressed(InputAction::Walk);
+                        if walking {
                             speed /= 1.7;
                         }
-                        if key_event.pressed(KeyCode::ControlLeft) {
-                            camera.translation = Vec3::new(0.0, 0.300, 0.0);
-                            if !player.is_crouched {
-                                //transform.translation.y -= 0.1345; // cuboid bug fix
-                                if let Some(mut capsule) = player_collider.as_capsule_mut() {
-                                    capsule.set_segment(Vec3::ZERO, Vec3::new(0.0, 0.130, 0.0));
-                                }
-                                player.is_crouched = true;
-                              }
+
+                        // sprinting drains stamina, once empty it has to recover a bit before sprinting again
+                        let wants_sprint = actions.pressed(InputAction::Sprint)
+                            && horizontal_velocity.dot(forward) > SPRINT_FORWARD
+                            && !walking
+                            && !player.is_crouched;
+                        let can_sprint = if player.is_sprinting {
+                            player.stamina > 0.0
                         } else {
-                            camera.translation = Vec3::new(0.0, 0.650, 0.0);
-                            if player.is_crouched {
-                                //transform.translation.y += 0.1345; // cuboid bug fix
-                                if let Some(mut capsule) = player_collider.as_capsule_mut() {
-                                    capsule.set_segment(Vec3::ZERO, Vec3::new(0.0, 0.450, 0.0));
-                                }
-                                player.is_crouched = false; 
-                              }
+                            player.stamina > player.max_stamina * 0.25
+                        };
+                        player.is_sprinting = wants_sprint && can_sprint;
+
+                        if player.is_sprinting {
+                            speed *= player.sprint_modifier;
+                            player.stamina = (player.stamina - delta_time).max(0.0);
+                        } else {
+                            player.stamina = (player.stamina + player.stamina_regen * delta_time).min(player.max_stamina);
+                        }
+
+                        // stays crouched if there's no room to stand up
+                        let crouch_pressed = actions.pressed(InputAction::Crouch);
+                        let ceiling_blocked = !crouch_pressed && player.crouch_amount > 0.0 && rapier_context.cast_ray(
+                            transform.translation,
+                            Vec3::Y,
+                            STANDING_SEGMENT + CAPSULE_RADIUS + 0.02,
+                            true,
+                            QueryFilter::only_fixed(),
+                        ).is_some();
+
+                        let crouch_target = if crouch_pressed || ceiling_blocked { 1.0 } else { 0.0 };
+                        let crouch_step = player.crouch_speed * delta_time;
+                        let previous_segment = capsule_segment(player.crouch_amount);
+
+                        player.crouch_amount = if player.crouch_amount < crouch_target {
+                            (player.crouch_amount + crouch_step).min(crouch_target)
+                        } else {
+                            (player.crouch_amount - crouch_step).max(crouch_target)
+                        };
+
+                        let segment = capsule_segment(player.crouch_amount);
+                        if segment != previous_segment {
+                            if let Some(mut capsule) = player_collider.as_capsule_mut() {
+                                capsule.set_segment(Vec3::ZERO, Vec3::new(0.0, segment, 0.0));
+                            }
+                            // crouching in the air lifts the legs instead of lowering the head
+                            if !player.is_grounded {
+                                transform.translation.y += previous_segment - segment;
+                            }
                         }
+
+                        camera.translation = Vec3::new(
+                            0.0,
+                            STANDING_CAMERA + (CROUCHED_CAMERA - STANDING_CAMERA) * player.crouch_amount,
+                            0.0
+                        );
+                        player.is_crouched = player.crouch_amount > 0.5;
+                        crouch_modifier *= 1.0 - 0.6 * player.crouch_amount;
                       
+                        // partial stick deflection walks slower, keys always move at full speed
+                        speed *= horizontal_velocity.length().min(1.0);
                         horizontal_velocity = horizontal_velocity.normalize_or_zero();
                         
-                        player.velocity.x = horizontal_velocity.x * speed * friction * crouch_modifier * delta_time;
-                        player.velocity.y *= friction;
-                        player.velocity.z = horizontal_velocity.z * speed * friction * crouch_modifier * delta_time;
-                        
-                        transform.translation += player.velocity;
+                        match settings.movement_model {
+                            MovementModel::Arcade => {
+                                if actions.just_pressed(InputAction::Jump) && player.is_grounded {
+                                    player.velocity.y = jump_height;
+                                }
+
+                                player.velocity.x = horizontal_velocity.x * speed * friction * crouch_modifier * delta_time;
+                                player.velocity.y *= friction;
+                                player.velocity.z = horizontal_velocity.z * speed * friction * crouch_modifier * delta_time;
+                                
+                                transform.translation += player.velocity;
+                            }
+                            MovementModel::Source => {
+                                // jumps pressed shortly before landing are queued
+                                if actions.just_pressed(InputAction::Jump) {
+                                    player.jump_queue = 0.1;
+                                }
+                                player.jump_queue = (player.jump_queue - delta_time).max(0.0);
+
+                                // the long grounded ray would keep friction on for the first part of a jump
+                                let on_ground = player.is_grounded && rapier_context.cast_ray(
+                                    transform.translation,
+                                    -Vec3::Y,
+                                    0.15,
+                                    true,
+                                    QueryFilter::only_fixed(),
+                                ).is_some();
+
+                                let jumped = on_ground && player.jump_queue > 0.0;
+                                if jumped {
+                                    player.velocity.y = jump_height * ARCADE_FRAME_RATE;
+                                    player.jump_queue = 0.0;
+                                }
+
+                                // no friction on the frame we jump, which is what allows bunny hopping
+                                if on_ground && !jumped {
+                                    apply_friction(&mut player, delta_time);
+                                }
+
+                                let wish_speed = speed * crouch_modifier;
+                                if on_ground && !jumped {
+                                    let accelerate = player.ground_accelerate;
+                                    accelerate_towards(&mut player, horizontal_velocity, wish_speed, wish_speed, accelerate, delta_time);
+                                } else {
+                                    let (max_air_speed, accelerate) = (player.max_air_speed, player.air_accelerate);
+                                    accelerate_towards(&mut player, horizontal_velocity, wish_speed.min(max_air_speed), wish_speed, accelerate, delta_time);
+                                }
+
+                                player.velocity.y *= (-JUMP_DAMPING * delta_time).exp();
+
+                                transform.translation += player.velocity * delta_time;
+                            }
+                        }
     
                         // short 'script' to make it easier for me to create the levels
                         //if key_event.just_pressed(KeyCode::KeyE) {
@@ -127,4 +235,46 @@ pub fn update(
             }
         }
     }
+}
+
+// Returns the capsule segment height for a crouch amount between 0 and 1
+fn capsule_segment(crouch_amount: f32) -> f32 {
+    STANDING_SEGMENT + (CROUCHED_SEGMENT - STANDING_SEGMENT) * crouch_amount
+}
+
+// Slows the player down on the ground, source style
+fn apply_friction(player: &mut PlayerController, delta_time: f32) {
+    let horizontal_speed = Vec2::new(player.velocity.x, player.velocity.z).length();
+    if horizontal_speed <= 0.0 {
+        return;
+    }
+
+    let control = horizontal_speed.max(player.stop_speed);
+    let drop = control * player.ground_friction * delta_time;
+    let scale = (horizontal_speed - drop).max(0.0) / horizontal_speed;
+
+    player.velocity.x *= scale;
+    player.velocity.z *= scale;
+}
+
+// Accelerates the player towards the wish direction, air strafing comes from
+// the capped wish speed while the acceleration still uses the full one
+fn accelerate_towards(
+    player: &mut PlayerController,
+    wish_direction: Vec3,
+    wish_speed: f32,
+    full_speed: f32,
+    accelerate: f32,
+    delta_time: f32
+) {
+    let current_speed = player.velocity.x * wish_direction.x + player.velocity.z * wish_direction.z;
+    let add_speed = wish_speed - current_speed;
+    if add_speed <= 0.0 {
+        return;
+    }
+
+    let accelerate_speed = (accelerate * full_speed * delta_time).min(add_speed);
+
+    player.velocity.x += accelerate_speed * wish_direction.x;
+    player.velocity.z += accelerate_speed * wish_direction.z;
 }
\ No newline at end of file
@@ -0,0 +1,428 @@
+use bevy::prelude::*;
+use crate::structs::{
+    Crosshair,
+    CrosshairPart,
+    CrosshairSettings,
+    GunController,
+    PlayerController,
+    ScopeOverlay,
+    Settings,
+    ShareCodeInput,
+    ShareCodeText,
+    TargetHitEvent
+};
+
+// Characters used by counter-strike share codes, in order of their value
+const SHARE_CODE_ALPHABET: &str = "ABCDEFGHJKLMNOPQRSTUVWXYZabcdefhijkmnopqrstuvwxyz23456789";
+
+const HIT_MARKER_TIME: f32 = 0.25;
+
+pub const CROSSHAIR_COLORS: [(&str, Color); 5] = [
+    ("RED", Color::rgb(0.98, 0.2, 0.2)),
+    ("GREEN", Color::rgb(0.2, 0.98, 0.2)),
+    ("YELLOW", Color::rgb(0.98, 0.98, 0.2)),
+    ("BLUE", Color::rgb(0.2, 0.2, 0.98)),
+    ("CYAN", Color::rgb(0.2, 0.98, 0.98))
+];
+
+// Spawns the crosshair centered on its parent node
+pub fn spawn_crosshair(
+    parent: &mut ChildBuilder
+) {
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(50.0),
+                top: Val::Percent(50.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Crosshair::default()
+    ))
+    .with_children(|parent| {
+        for part in [
+            CrosshairPart::Top,
+            CrosshairPart::Bottom,
+            CrosshairPart::Left,
+            CrosshairPart::Right,
+            CrosshairPart::Dot,
+            CrosshairPart::HitTopLeft,
+            CrosshairPart::HitTopRight,
+            CrosshairPart::HitBottomLeft,
+            CrosshairPart::HitBottomRight
+        ] {
+            // hit marker lines point away from the center
+            let rotation = match part {
+                CrosshairPart::HitTopLeft | CrosshairPart::HitBottomRight => Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+                CrosshairPart::HitTopRight | CrosshairPart::HitBottomLeft => Quat::from_rotation_z(-std::f32::consts::FRAC_PI_4),
+                _ => Quat::IDENTITY
+            };
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        ..Default::default()
+                    },
+                    transform: Transform::from_rotation(rotation),
+                    ..Default::default()
+                },
+                Outline::new(Val::Px(0.0), Val::Px(0.0), Color::NONE),
+                part
+            ));
+        }
+    });
+}
+
+// Spawns the hidden scope overlay, a clear square in the middle with black bars to the sides
+pub fn spawn_scope_overlay(
+    parent: &mut ChildBuilder
+) {
+    let bar = NodeBundle {
+        style: Style {
+            flex_grow: 1.0,
+            height: Val::Percent(100.0),
+            ..Default::default()
+        },
+        background_color: Color::BLACK.into(),
+        ..Default::default()
+    };
+
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Row,
+                ..Default::default()
+            },
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        ScopeOverlay
+    ))
+    .with_children(|parent| {
+        parent.spawn(bar.clone());
+        parent.spawn(NodeBundle {
+            style: Style {
+                height: Val::Percent(100.0),
+                aspect_ratio: Some(1.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            // thin reticle lines across the whole lens
+            for (width, height) in [(Val::Percent(100.0), Val::Px(1.0)), (Val::Px(1.0), Val::Percent(100.0))] {
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: if width == Val::Px(1.0) { Val::Percent(50.0) } else { Val::Px(0.0) },
+                        top: if height == Val::Px(1.0) { Val::Percent(50.0) } else { Val::Px(0.0) },
+                        width,
+                        height,
+                        ..Default::default()
+                    },
+                    background_color: Color::BLACK.into(),
+                    ..Default::default()
+                });
+            }
+        });
+        parent.spawn(bar);
+    });
+}
+
+// Shows the scope overlay once a scoped weapon is fully aimed, hiding the crosshair and view model
+#[allow(clippy::type_complexity)]
+pub fn update_scope_overlay(
+    mut gun_query: Query<(&GunController, &mut Visibility), (Without<ScopeOverlay>, Without<Crosshair>)>,
+    mut overlay_query: Query<&mut Visibility, (With<ScopeOverlay>, Without<Crosshair>)>,
+    mut crosshair_query: Query<&mut Visibility, With<Crosshair>>
+) {
+    let mut scoped = false;
+    for (gun_controller, mut visibility) in gun_query.iter_mut() {
+        scoped = gun_controller.scope.is_some() && gun_controller.aim_amount >= 1.0;
+        let target = if scoped { Visibility::Hidden } else { Visibility::Inherited };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+
+    let (overlay, crosshair) = if scoped {
+        (Visibility::Inherited, Visibility::Hidden)
+    } else {
+        (Visibility::Hidden, Visibility::Inherited)
+    };
+    for mut visibility in overlay_query.iter_mut() {
+        if *visibility != overlay {
+            *visibility = overlay;
+        }
+    }
+    for mut visibility in crosshair_query.iter_mut() {
+        if *visibility != crosshair {
+            *visibility = crosshair;
+        }
+    }
+}
+
+// Flashes the hit marker when a target is hit
+pub fn show_hit_marker(
+    mut hit_events: EventReader<TargetHitEvent>,
+    mut crosshair_query: Query<&mut Crosshair>
+) {
+    for hit_event in hit_events.read() {
+        for mut crosshair in crosshair_query.iter_mut() {
+            crosshair.hit_marker_time = HIT_MARKER_TIME;
+            crosshair.hit_marker_kill = hit_event.killed;
+        }
+    }
+}
+
+// Lays out the crosshair lines from the settings, widening the gap while moving or shooting
+pub fn update_crosshair(
+    settings: Res<Settings>,
+    time: Res<Time>,
+    player_query: Query<(&GlobalTransform, &PlayerController)>,
+    gun_query: Query<&GunController>,
+    mut crosshair_query: Query<(&mut Crosshair, &Children)>,
+    mut part_query: Query<(&CrosshairPart, &mut Style, &mut BackgroundColor, &mut Outline)>
+) {
+    let crosshair_settings = settings.crosshair;
+    let delta_time = time.delta_seconds();
+    let shooting = gun_query.iter().any(|gun_controller| gun_controller.shooting);
+
+    for (mut crosshair, children) in crosshair_query.iter_mut() {
+        let mut target_spread = 0.0;
+        if let Ok((player_transform, player_controller)) = player_query.get_single() {
+            let position = player_transform.translation();
+            if let Some(last_position) = crosshair.last_position {
+                if delta_time > 0.0 {
+                    let speed = (position - last_position).xz().length() / delta_time;
+                    target_spread += (speed / player_controller.speed).min(1.5) * 6.0;
+                }
+            }
+            crosshair.last_position = Some(position);
+            if shooting {
+                target_spread += 4.0;
+            }
+        }
+        if !crosshair_settings.dynamic {
+            target_spread = 0.0;
+        }
+        crosshair.spread += (target_spread - crosshair.spread) * (12.0 * delta_time).min(1.0);
+        crosshair.hit_marker_time = (crosshair.hit_marker_time - delta_time).max(0.0);
+
+        let hit_marker_alpha = crosshair.hit_marker_time / HIT_MARKER_TIME;
+        let hit_marker_color = if crosshair.hit_marker_kill {
+            Color::rgba(1.0, 0.2, 0.2, hit_marker_alpha)
+        } else {
+            Color::rgba(1.0, 1.0, 1.0, hit_marker_alpha)
+        };
+        let hit_marker_distance = 10.0 + crosshair.spread;
+
+        let gap = crosshair_settings.gap + crosshair.spread;
+        let length = crosshair_settings.length;
+        let thickness = crosshair_settings.thickness;
+
+        for child in children.iter() {
+            let Ok((part, mut style, mut background_color, mut outline)) = part_query.get_mut(*child) else { continue };
+
+            // offsets from the center of the screen to the top left corner of the part
+            let (left, top, width, height) = match part {
+                CrosshairPart::Top => (-thickness / 2.0, -gap - length, thickness, length),
+                CrosshairPart::Bottom => (-thickness / 2.0, gap, thickness, length),
+                CrosshairPart::Left => (-gap - length, -thickness / 2.0, length, thickness),
+                CrosshairPart::Right => (gap, -thickness / 2.0, length, thickness),
+                CrosshairPart::Dot => (-thickness / 2.0, -thickness / 2.0, thickness, thickness),
+                CrosshairPart::HitTopLeft => hit_marker_line(-hit_marker_distance, -hit_marker_distance),
+                CrosshairPart::HitTopRight => hit_marker_line(hit_marker_distance, -hit_marker_distance),
+                CrosshairPart::HitBottomLeft => hit_marker_line(-hit_marker_distance, hit_marker_distance),
+                CrosshairPart::HitBottomRight => hit_marker_line(hit_marker_distance, hit_marker_distance)
+            };
+
+            style.left = Val::Px(left);
+            style.top = Val::Px(top);
+            style.width = Val::Px(width);
+            style.height = Val::Px(height);
+
+            let is_hit_marker = matches!(
+                part,
+                CrosshairPart::HitTopLeft | CrosshairPart::HitTopRight | CrosshairPart::HitBottomLeft | CrosshairPart::HitBottomRight
+            );
+            if is_hit_marker {
+                let visible = crosshair_settings.hit_marker && crosshair.hit_marker_time > 0.0;
+                background_color.0 = if visible { hit_marker_color } else { Color::NONE };
+                outline.color = Color::NONE;
+                continue;
+            }
+
+            let visible = if *part == CrosshairPart::Dot { crosshair_settings.dot } else { length > 0.0 };
+            background_color.0 = if visible { crosshair_settings.color } else { Color::NONE };
+            outline.width = Val::Px(crosshair_settings.outline_thickness);
+            outline.color = if visible && crosshair_settings.outline {
+                Color::rgba(0.0, 0.0, 0.0, crosshair_settings.color.a())
+            } else {
+                Color::NONE
+            };
+        }
+    }
+}
+
+// Returns the left, top, width and height of a hit marker line centered at an offset from the middle
+fn hit_marker_line(x: f32, y: f32) -> (f32, f32, f32, f32) {
+    let length = 8.0;
+    let thickness = 2.0;
+    (x - length / 2.0, y - thickness / 2.0, length, thickness)
+}
+
+// Reads a typed share code, enter imports it and escape cancels
+pub fn capture_share_code(
+    key_event: Res<ButtonInput<KeyCode>>,
+    mut character_events: EventReader<ReceivedCharacter>,
+    mut share_code: ResMut<ShareCodeInput>,
+    mut settings: ResMut<Settings>
+) {
+    if !share_code.typing {
+        character_events.clear();
+        return;
+    }
+
+    if key_event.just_pressed(KeyCode::Escape) {
+        share_code.typing = false;
+        return;
+    }
+
+    if key_event.just_pressed(KeyCode::Enter) {
+        share_code.typing = false;
+        match decode_share_code(&share_code.code) {
+            Ok(crosshair_settings) => {
+                // share codes have no hit marker, keep the current choice
+                settings.crosshair = CrosshairSettings {
+                    hit_marker: settings.crosshair.hit_marker,
+                    ..crosshair_settings
+                };
+                share_code.message = Some("IMPORTED".to_string());
+            }
+            Err(error) => share_code.message = Some(error)
+        }
+        return;
+    }
+
+    if key_event.just_pressed(KeyCode::Backspace) {
+        share_code.code.pop();
+    }
+
+    for character_event in character_events.read() {
+        for character in character_event.char.chars() {
+            if character.is_ascii_alphanumeric() || character == '-' {
+                share_code.code.push(character);
+            }
+        }
+    }
+}
+
+// Keeps the share code text in sync with what is being typed
+pub fn update_share_code_text(
+    share_code: Res<ShareCodeInput>,
+    mut share_code_text_query: Query<&mut Text, With<ShareCodeText>>
+) {
+    if share_code.is_changed() {
+        for mut share_code_text in share_code_text_query.iter_mut() {
+            share_code_text.sections[0].value = share_code_label(&share_code);
+        }
+    }
+}
+
+pub fn share_code_label(share_code: &ShareCodeInput) -> String {
+    if share_code.typing {
+        format!("{}_", share_code.code)
+    } else if let Some(message) = &share_code.message {
+        message.clone()
+    } else if share_code.code.is_empty() {
+        "CSGO-XXXXX-XXXXX-XXXXX-XXXXX-XXXXX".to_string()
+    } else {
+        share_code.code.clone()
+    }
+}
+
+// Steps through the preset colours, keeping the current alpha
+pub fn cycle_color(color: Color, step: f32) -> Color {
+    let index = CROSSHAIR_COLORS.iter().position(|(_, preset)| preset.rgb_to_vec3() == color.rgb_to_vec3());
+    let count = CROSSHAIR_COLORS.len();
+    let next = match index {
+        Some(index) if step > 0.0 => (index + 1) % count,
+        Some(index) => (index + count - 1) % count,
+        None => 0
+    };
+    CROSSHAIR_COLORS[next].1.with_a(color.a())
+}
+
+pub fn color_name(color: Color) -> String {
+    CROSSHAIR_COLORS.iter()
+        .find(|(_, preset)| preset.rgb_to_vec3() == color.rgb_to_vec3())
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| {
+            let [red, green, blue, _] = color.as_rgba_u8();
+            format!("{} {} {}", red, green, blue)
+        })
+}
+
+// Decodes a counter-strike crosshair share code, sizes are converted to roughly match cs at 1080p
+pub fn decode_share_code(code: &str) -> Result<CrosshairSettings, String> {
+    let characters: Vec<char> = code.trim()
+        .trim_start_matches("CSGO")
+        .chars()
+        .filter(|character| *character != '-')
+        .collect();
+    if characters.len() != 25 {
+        return Err("CODE NEEDS 25 CHARACTERS".to_string());
+    }
+
+    // the code is a base 57 number with the least significant character first
+    let mut bytes = [0u8; 18];
+    for character in characters.iter().rev() {
+        let Some(value) = SHARE_CODE_ALPHABET.find(*character) else {
+            return Err(format!("INVALID CHARACTER {}", character));
+        };
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut().rev() {
+            let total = *byte as u32 * 57 + carry;
+            *byte = (total & 0xff) as u8;
+            carry = total >> 8;
+        }
+        if carry != 0 {
+            return Err("INVALID CODE".to_string());
+        }
+    }
+
+    let checksum = bytes[1..].iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if checksum != bytes[0] {
+        return Err("NOT A CROSSHAIR CODE".to_string());
+    }
+
+    let gap = bytes[2] as i8 as f32 / 10.0;
+    let outline_thickness = bytes[3] as f32 / 2.0;
+    let alpha = if bytes[13] & 0x40 != 0 { bytes[7] } else { 200 };
+    let color = match bytes[10] & 7 {
+        index @ 0..=4 => CROSSHAIR_COLORS[index as usize].1,
+        _ => Color::rgb_u8(bytes[4], bytes[5], bytes[6])
+    };
+    let thickness = bytes[12] as f32 / 10.0;
+    let length = (((bytes[15] as u32 & 0x1f) << 8) | bytes[14] as u32) as f32 / 10.0;
+    let style = (bytes[13] & 0xf) >> 1;
+
+    Ok(CrosshairSettings {
+        gap: (gap + 4.0).clamp(-10.0, 30.0),
+        length: (length * 2.0).clamp(0.0, 40.0),
+        thickness: (thickness * 2.0).round().clamp(1.0, 10.0),
+        outline: bytes[10] & 8 != 0,
+        outline_thickness: outline_thickness.clamp(1.0, 3.0),
+        color: color.with_a(alpha as f32 / 255.0),
+        dot: bytes[13] & 0x10 != 0,
+        // the default and classic dynamic styles move with the player
+        dynamic: matches!(style, 0 | 2 | 3),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,348 @@
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::view::RenderLayers
+};
+use crate::{
+    LevelState,
+    gunplay::MUZZLE_OFFSET,
+    replay::level_number,
+    structs::{
+        BulletImpactEvent,
+        Decal,
+        EffectsController,
+        GameEntity,
+        Ghost,
+        GunController,
+        MuzzleFlash,
+        ReplayRecorder,
+        Settings,
+        ShotFiredEvent,
+        Tracer
+    }
+};
+
+const MAX_DECALS: usize = 64;
+const DECAL_LIFETIME: f32 = 10.0;
+const DECAL_FADE_TIME: f32 = 2.0;
+const DECAL_FADE_STEPS: usize = 16;
+const TRACER_SPEED: f32 = 300.0;
+const TRACER_LENGTH: f32 = 0.6;
+const MUZZLE_FLASH_TIME: f32 = 0.05;
+// Enough to light the walls right around the player, not the whole room
+const MUZZLE_LIGHT_INTENSITY: f32 = 1500.0;
+const MUZZLE_LIGHT_RANGE: f32 = 1.5;
+// The player capsule goes 0.55 up from its origin, the camera sits at 0.65
+const GHOST_CENTER: f32 = 0.225;
+const GHOST_EYE: f32 = 0.65;
+// Ticks a ghost shot stays drawn for
+const GHOST_SHOT_TICKS: usize = 6;
+
+// Creates the meshes and materials shared by the shooting effects
+pub fn load_effects(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+) {
+    let decal_mesh = meshes.add(Circle::new(0.015));
+    // decals share these instead of owning a material each
+    let decal_materials = (0..=DECAL_FADE_STEPS).map(|step| materials.add(StandardMaterial {
+        base_color: Color::rgba(0.03, 0.03, 0.03, step as f32 / DECAL_FADE_STEPS as f32),
+        perceptual_roughness: 1.0,
+        alpha_mode: AlphaMode::Blend,
+        depth_bias: 10.0,
+        ..Default::default()
+    })).collect();
+    let tracer_mesh = meshes.add(Cuboid::new(0.006, 0.006, 1.0));
+    let tracer_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(1.0, 0.85, 0.5, 0.8),
+        emissive: Color::rgb(4.0, 3.0, 1.5),
+        alpha_mode: AlphaMode::Add,
+        unlit: true,
+        ..Default::default()
+    });
+    let flash_mesh = meshes.add(Rectangle::new(0.12, 0.12));
+    let flash_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(1.0, 0.7, 0.3, 0.9),
+        emissive: Color::rgb(6.0, 4.0, 1.5),
+        alpha_mode: AlphaMode::Add,
+        unlit: true,
+        ..Default::default()
+    });
+
+    let ghost_mesh = meshes.add(Capsule3d::new(0.1, 0.45));
+    let ghost_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.4, 0.8, 1.0, 0.35),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..Default::default()
+    });
+
+    commands.insert_resource(EffectsController {
+        decal_mesh: Some(decal_mesh),
+        decal_materials,
+        tracer_mesh: Some(tracer_mesh),
+        tracer_material: Some(tracer_material),
+        flash_mesh: Some(flash_mesh),
+        flash_material: Some(flash_material),
+        ghost_mesh: Some(ghost_mesh),
+        ghost_material: Some(ghost_material)
+    });
+}
+
+// Spawns the hidden muzzle flash at the end of the view model barrel
+pub fn spawn_muzzle_flash(
+    parent: &mut ChildBuilder,
+    effects: &EffectsController,
+    position: Vec3
+) {
+    parent.spawn((
+        PbrBundle {
+            mesh: effects.flash_mesh.clone().unwrap_or_default(),
+            material: effects.flash_material.clone().unwrap_or_default(),
+            transform: Transform::from_translation(position),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        RenderLayers::layer(1),
+        MuzzleFlash { time_left: 0.0 }
+    ))
+    .with_children(|parent| {
+        parent.spawn(PointLightBundle {
+            point_light: PointLight {
+                color: Color::rgb(1.0, 0.7, 0.3),
+                intensity: 0.0,
+                range: MUZZLE_LIGHT_RANGE,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    });
+}
+
+// Leaves a bullet hole where a shot hit the map, reusing the oldest one when there are too many
+pub fn spawn_decals(
+    mut commands: Commands,
+    mut impact_events: EventReader<BulletImpactEvent>,
+    mut decal_query: Query<(&mut Decal, &mut Transform, &mut Handle<StandardMaterial>)>,
+    effects: Res<EffectsController>,
+    settings: Res<Settings>
+) {
+    for impact_event in impact_events.read() {
+        if !settings.bullet_decals {
+            continue;
+        }
+
+        // the circle faces +z, so it looks away from the normal
+        let up = if impact_event.normal.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+        let transform = Transform::from_translation(impact_event.position + impact_event.normal * 0.002)
+            .looking_to(-impact_event.normal, up);
+
+        if decal_query.iter().count() >= MAX_DECALS {
+            let oldest = decal_query.iter_mut().max_by(|a, b| a.0.age.total_cmp(&b.0.age));
+            if let Some((mut decal, mut decal_transform, mut material)) = oldest {
+                decal.age = 0.0;
+                *decal_transform = transform;
+                *material = decal_material(&effects, 1.0);
+            }
+            continue;
+        }
+
+        commands.spawn((
+            PbrBundle {
+                mesh: effects.decal_mesh.clone().unwrap_or_default(),
+                material: decal_material(&effects, 1.0),
+                transform,
+                ..Default::default()
+            },
+            Decal { age: 0.0 },
+            GameEntity
+        ));
+    }
+}
+
+// Fades bullet holes out at the end of their lifetime
+pub fn update_decals(
+    time: Res<Time>,
+    mut decal_query: Query<(&mut Decal, &mut Visibility, &mut Handle<StandardMaterial>)>,
+    effects: Res<EffectsController>
+) {
+    for (mut decal, mut visibility, mut material) in decal_query.iter_mut() {
+        if decal.age >= DECAL_LIFETIME {
+            continue;
+        }
+        decal.age += time.delta_seconds();
+
+        let alpha = ((DECAL_LIFETIME - decal.age) / DECAL_FADE_TIME).clamp(0.0, 1.0);
+        let faded = decal_material(&effects, alpha);
+        if *material != faded {
+            *material = faded;
+        }
+        *visibility = if alpha > 0.0 { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+// The shared decal material closest to an alpha
+fn decal_material(effects: &EffectsController, alpha: f32) -> Handle<StandardMaterial> {
+    let step = (alpha * DECAL_FADE_STEPS as f32).round() as usize;
+    effects.decal_materials.get(step.min(DECAL_FADE_STEPS)).cloned().unwrap_or_default()
+}
+
+// Spawns a tracer streak for every shot
+pub fn spawn_tracers(
+    mut commands: Commands,
+    mut shot_events: EventReader<ShotFiredEvent>,
+    effects: Res<EffectsController>,
+    settings: Res<Settings>
+) {
+    for shot_event in shot_events.read() {
+        if !settings.tracers || shot_event.origin.distance(shot_event.end) < TRACER_LENGTH {
+            continue;
+        }
+
+        commands.spawn((
+            PbrBundle {
+                mesh: effects.tracer_mesh.clone().unwrap_or_default(),
+                material: effects.tracer_material.clone().unwrap_or_default(),
+                transform: Transform::from_translation(shot_event.origin)
+                    .looking_at(shot_event.end, Vec3::Y)
+                    .with_scale(Vec3::new(1.0, 1.0, TRACER_LENGTH)),
+                ..Default::default()
+            },
+            Tracer {
+                start: shot_event.origin,
+                end: shot_event.end,
+                traveled: 0.0
+            },
+            GameEntity
+        ));
+    }
+}
+
+// Moves tracers along their shot and removes them once they land
+pub fn update_tracers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tracer_query: Query<(Entity, &mut Tracer, &mut Transform)>
+) {
+    for (entity, mut tracer, mut transform) in tracer_query.iter_mut() {
+        tracer.traveled += TRACER_SPEED * time.delta_seconds();
+
+        let distance = tracer.start.distance(tracer.end);
+        if tracer.traveled >= distance {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let direction = (tracer.end - tracer.start) / distance;
+        let head = (tracer.traveled + TRACER_LENGTH).min(distance);
+        transform.translation = tracer.start + direction * (tracer.traveled + head) / 2.0;
+        transform.scale.z = head - tracer.traveled;
+    }
+}
+
+// Lights up the muzzle flash when the gun fires
+pub fn flash_muzzle(
+    mut shot_events: EventReader<ShotFiredEvent>,
+    mut flash_query: Query<(&mut MuzzleFlash, &mut Transform)>,
+    gun_query: Query<&Transform, (With<GunController>, Without<MuzzleFlash>)>,
+    settings: Res<Settings>,
+    time: Res<Time>
+) {
+    if shot_events.read().count() == 0 || !settings.muzzle_flash {
+        return;
+    }
+
+    let muzzle = gun_query.iter().next()
+        .map(|gun_transform| gun_transform.translation + MUZZLE_OFFSET);
+
+    for (mut muzzle_flash, mut transform) in flash_query.iter_mut() {
+        muzzle_flash.time_left = MUZZLE_FLASH_TIME;
+        if let Some(muzzle) = muzzle {
+            transform.translation = muzzle;
+        }
+        // a different roll every shot so consecutive flashes look different
+        transform.rotation = Quat::from_rotation_z((time.elapsed_seconds() * 7919.0) % std::f32::consts::TAU);
+    }
+}
+
+// Hides the muzzle flash again after a moment
+pub fn update_muzzle_flash(
+    time: Res<Time>,
+    mut flash_query: Query<(&mut MuzzleFlash, &mut Visibility, &Children)>,
+    mut light_query: Query<&mut PointLight>
+) {
+    for (mut muzzle_flash, mut visibility, children) in flash_query.iter_mut() {
+        muzzle_flash.time_left = (muzzle_flash.time_left - time.delta_seconds()).max(0.0);
+        let visible = muzzle_flash.time_left > 0.0;
+
+        let target_visibility = if visible { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != target_visibility {
+            *visibility = target_visibility;
+        }
+        for child in children.iter() {
+            if let Ok(mut light) = light_query.get_mut(*child) {
+                light.intensity = if visible { MUZZLE_LIGHT_INTENSITY } else { 0.0 };
+            }
+        }
+    }
+}
+
+
+// Moves a see-through ghost along the personal best of the level, in step with the current run,
+// and draws where it looks and shoots
+pub fn update_ghost(
+    mut commands: Commands,
+    mut ghost_query: Query<(Entity, &mut Transform), With<Ghost>>,
+    mut gizmos: Gizmos,
+    recorder: Res<ReplayRecorder>,
+    current_level: Res<State<LevelState>>,
+    effects: Res<EffectsController>,
+    settings: Res<Settings>
+) {
+    let best = level_number(current_level.get())
+        .and_then(|level| recorder.best.get(&level))
+        .filter(|best| settings.ghost && !best.poses.is_empty());
+
+    let Some(best) = best else {
+        for (entity, _) in ghost_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    };
+
+    // the ghost waits where it started playing until this run starts playing too
+    let playing_start = best.poses.len().saturating_sub(best.ticks as usize);
+    let ticks = recorder.current.as_ref().map_or(0, |current| current.ticks as usize);
+    let index = (playing_start + ticks).min(best.poses.len() - 1);
+    let pose = best.poses[index];
+
+    let transform = Transform::from_translation(pose.translation + Vec3::Y * GHOST_CENTER)
+        .with_rotation(Quat::from_axis_angle(Vec3::Y, pose.yaw));
+    match ghost_query.get_single_mut() {
+        Ok((_, mut ghost_transform)) => *ghost_transform = transform,
+        Err(_) => {
+            commands.spawn((
+                PbrBundle {
+                    mesh: effects.ghost_mesh.clone().unwrap_or_default(),
+                    material: effects.ghost_material.clone().unwrap_or_default(),
+                    transform,
+                    ..Default::default()
+                },
+                NotShadowCaster,
+                Ghost,
+                GameEntity
+            ));
+        }
+    }
+
+    let eye = pose.translation + Vec3::Y * GHOST_EYE;
+    let looking = Quat::from_axis_angle(Vec3::Y, pose.yaw) * Quat::from_axis_angle(Vec3::X, pose.pitch) * -Vec3::Z;
+    gizmos.line(eye, eye + looking * 0.5, Color::rgb(0.4, 0.8, 1.0));
+
+    for shot_pose in &best.poses[index.saturating_sub(GHOST_SHOT_TICKS)..=index] {
+        if let Some(shot) = shot_pose.shot {
+            gizmos.line(shot_pose.translation + Vec3::Y * GHOST_EYE, shot.end, Color::rgb(1.0, 0.6, 0.2));
+        }
+    }
+}
\ No newline at end of file
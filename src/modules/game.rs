@@ -17,7 +17,9 @@ use crate::structs::{
     LevelController,
     StartButton,
     LastState,
-    MapImage
+    MapImage,
+    InputAction,
+    Settings
 };
 use bevy::{
     window::CursorGrabMode,
@@ -29,17 +31,22 @@ use bevy::{
     }, 
 };
 
+pub const MAX_VERTICAL_ANGLE: f32 = std::f32::consts::FRAC_PI_2 - 0.02;
+pub const TARGET_RADIUS: f32 = 0.1;
+
 // Runs on startup and spawns overlay text
 pub fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut window: Query<&mut Window>,
 ) {
-    let mut window = window.get_single_mut().unwrap();
     let text_font = asset_server.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf");
 
-    window.cursor.visible = false;
-    window.cursor.grab_mode = CursorGrabMode::Locked;
+    // there is no window when running headless
+    if let Ok(mut window) = window.get_single_mut() {
+        window.cursor.visible = false;
+        window.cursor.grab_mode = CursorGrabMode::Locked;
+    }
 
     // spawn fps overlay text
     commands.spawn((
@@ -119,7 +126,7 @@ pub fn setup(
 
 // Handles pause menu and the timer once finished
 pub fn update(
-    key_event: Res<ButtonInput<KeyCode>>,
+    actions: Res<ButtonInput<InputAction>>,
     current_state: Res<State<GameState>>,
     mut last_state: ResMut<LastState>,
     mut next_state: ResMut<NextState<GameState>>,
@@ -128,11 +135,10 @@ pub fn update(
 ) {
     let state = *current_state.get();
 
-    if key_event.just_pressed(KeyCode::Escape) {
-        if state == GameState::Playing || state == GameState::Start || state == GameState::Won {
-            last_state.state = Some(state);
-            next_state.set(GameState::PauseMenu);
-        }
+    if actions.just_pressed(InputAction::Pause)
+        && (state == GameState::Playing || state == GameState::Start || state == GameState::Won) {
+        last_state.state = Some(state);
+        next_state.set(GameState::PauseMenu);
     }
 
     if time_controller.is_finished() {
@@ -142,6 +148,7 @@ pub fn update(
 }
 
 // Runs every level change, handles and spawns targets/start button/map image
+#[allow(clippy::too_many_arguments)]
 pub fn initiate_level(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -220,7 +227,7 @@ pub fn initiate_level(
             map_image_handle = asset_server.load("levels/level1.png").into();
 
             // spawn targets
-            for target_position in levels.level_1_pos.iter() {
+            for (id, target_position) in levels.level_1_pos.iter().enumerate() {
                 commands.spawn((
                     PbrBundle {
                         mesh: ball_mesh.clone(),
@@ -230,7 +237,7 @@ pub fn initiate_level(
                     },
                     AsyncCollider { ..Default::default() },
                     RigidBody::Fixed,
-                    TargetController { health: 1 },
+                    TargetController { health: 1, id },
                     GameEntity
                 ));
             }
@@ -243,7 +250,7 @@ pub fn initiate_level(
             map_image_handle = asset_server.load("levels/level2.png").into();
 
             // spawn targets
-            for target_position in levels.level_2_pos.iter() {
+            for (id, target_position) in levels.level_2_pos.iter().enumerate() {
                 commands.spawn((
                     PbrBundle {
                         mesh: ball_mesh.clone(),
@@ -253,7 +260,7 @@ pub fn initiate_level(
                     },
                     AsyncCollider { ..Default::default() },
                     RigidBody::Fixed,
-                    TargetController { health: 1 },
+                    TargetController { health: 1, id },
                     GameEntity
                 ));
             }
@@ -266,7 +273,7 @@ pub fn initiate_level(
             map_image_handle = asset_server.load("levels/level3.png").into();
 
             // spawn targets
-            for target_position in levels.level_3_pos.iter() {
+            for (id, target_position) in levels.level_3_pos.iter().enumerate() {
                 commands.spawn((
                     PbrBundle {
                         mesh: ball_mesh.clone(),
@@ -276,7 +283,7 @@ pub fn initiate_level(
                     },
                     AsyncCollider { ..Default::default() },
                     RigidBody::Fixed,
-                    TargetController { health: 1 },
+                    TargetController { health: 1, id },
                     GameEntity
                 ));
             }
@@ -289,7 +296,7 @@ pub fn initiate_level(
             map_image_handle = asset_server.load("levels/level4.png").into();
 
             // spawn targets
-            for target_position in levels.level_4_pos.iter() {
+            for (id, target_position) in levels.level_4_pos.iter().enumerate() {
                 commands.spawn((
                     PbrBundle {
                         mesh: ball_mesh.clone(),
@@ -299,7 +306,7 @@ pub fn initiate_level(
                     },
                     AsyncCollider { ..Default::default() },
                     RigidBody::Fixed,
-                    TargetController { health: 1 },
+                    TargetController { health: 1, id },
                     GameEntity
                 ));
             }
@@ -312,7 +319,7 @@ pub fn initiate_level(
             map_image_handle = asset_server.load("levels/level5.png").into();
 
             // spawn targets
-            for target_position in levels.level_5_pos.iter() {
+            for (id, target_position) in levels.level_5_pos.iter().enumerate() {
                 commands.spawn((
                     PbrBundle {
                         mesh: ball_mesh.clone(),
@@ -322,7 +329,7 @@ pub fn initiate_level(
                     },
                     AsyncCollider { ..Default::default() },
                     RigidBody::Fixed,
-                    TargetController { health: 1 },
+                    TargetController { health: 1, id },
                     GameEntity
                 ));
             }
@@ -367,7 +374,7 @@ pub fn change_level_state(
     mut next_state: ResMut<NextState<GameState>>
 ) {
     // check if all targets are destroyed
-    if target_query.iter().count() <= 0 {
+    if target_query.iter().count() == 0 {
         match current_level.get() {
             LevelState::NoLevel => {}
             LevelState::Failed => {
@@ -399,19 +406,21 @@ pub fn change_level_state(
 }
 
 // This method handles mouse motion
+#[allow(clippy::type_complexity)]
 pub fn mouse_callback(
     mut player_query: Query<&mut Transform, With<PlayerController>>,
     mut camera_query: Query<(&mut CameraController, &mut Transform),
                             (With<Camera>, Without<PlayerController>)>,
-    mut mouse_motion_events: EventReader<MouseMotion>
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    settings: Res<Settings>
 ) {
     for event in mouse_motion_events.read() {
         for (mut camera, mut camera_transform) in camera_query.iter_mut() {
             for mut player_transform in player_query.iter_mut() {
-                const MAX_VERTICAL_ANGLE: f32 = std::f32::consts::FRAC_PI_2 - 0.02;
-                
-                camera.pitch += -event.delta.y * camera.sensitivity;
-                camera.yaw += -event.delta.x * camera.sensitivity;
+                let sensitivity = camera.sensitivity * settings.sensitivity * camera.zoom_sensitivity;
+
+                camera.pitch += -event.delta.y * sensitivity * settings.sensitivity_y;
+                camera.yaw += -event.delta.x * sensitivity * settings.sensitivity_x;
                 
                 camera.pitch = camera.pitch.clamp(-MAX_VERTICAL_ANGLE, MAX_VERTICAL_ANGLE);
     
@@ -426,13 +435,14 @@ pub fn mouse_callback(
 pub fn change_cursor_state(
     mut window: Query<&mut Window>
 ) {
-    let mut window = window.get_single_mut().unwrap();
-
-    window.cursor.visible = false;
-    window.cursor.grab_mode = CursorGrabMode::Locked;
+    if let Ok(mut window) = window.get_single_mut() {
+        window.cursor.visible = false;
+        window.cursor.grab_mode = CursorGrabMode::Locked;
+    }
 }
 
 // Handles the text overlay
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn diagnostics(
     diagnostics: Res<DiagnosticsStore>,
     mut fps_text_query: Query<&mut Text, (With<FpsText>, Without<TargetText>, Without<TimeText>, Without<LevelText>)>,
@@ -467,7 +477,7 @@ pub fn diagnostics(
         "FPS: {}", fps.round()
     );
     level_text.sections[0].value = format!(
-        "LEVEL {} - {}", level_info[0].to_string(), level_info[1].to_string()
+        "LEVEL {} - {}", level_info[0], level_info[1]
     );
     target_text.sections[0].value = format!(
         "TARGETS LEFT: {}", targets_left
@@ -496,4 +506,13 @@ pub fn in_playing_state(game_state: Res<State<GameState>>) -> bool {
 
 pub fn in_won_state(game_state: Res<State<GameState>>) -> bool {
     game_state.get() == &GameState::Won
-}
\ No newline at end of file
+}
+
+pub fn in_replay_state(game_state: Res<State<GameState>>) -> bool {
+    game_state.get() == &GameState::Replay
+}
+
+// Start, playing and won all have the player walking around the map
+pub fn in_game_state(game_state: Res<State<GameState>>) -> bool {
+    matches!(game_state.get(), GameState::Start | GameState::Playing | GameState::Won)
+}
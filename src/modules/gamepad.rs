@@ -0,0 +1,161 @@
+use bevy::{
+    prelude::*,
+    window::CursorMoved
+};
+use crate::{
+    game::MAX_VERTICAL_ANGLE,
+    structs::{
+        AnalogInput,
+        CameraController,
+        MenuFocus,
+        PlayerController,
+        Settings
+    }
+};
+
+const FOCUS_COLOR: Color = Color::rgb(1.0, 0.8, 0.3);
+
+// Reads the sticks of the first connected gamepad
+pub fn update_sticks(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    settings: Res<Settings>,
+    mut analog_input: ResMut<AnalogInput>
+) {
+    analog_input.movement = Vec2::ZERO;
+    analog_input.look = Vec2::ZERO;
+
+    let Some(gamepad) = gamepads.iter().next() else { return };
+    let read = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.0);
+
+    let left_stick = Vec2::new(read(GamepadAxisType::LeftStickX), read(GamepadAxisType::LeftStickY));
+    let right_stick = Vec2::new(read(GamepadAxisType::RightStickX), read(GamepadAxisType::RightStickY));
+
+    analog_input.movement = apply_response(left_stick, settings.gamepad.deadzone, 1.0);
+    analog_input.look = apply_response(right_stick, settings.gamepad.deadzone, settings.gamepad.look_curve);
+}
+
+// Applies a radial deadzone and rescales the rest of the stick range through a power curve
+pub fn apply_response(stick: Vec2, deadzone: f32, curve: f32) -> Vec2 {
+    let magnitude = stick.length();
+    if magnitude <= deadzone || deadzone >= 1.0 {
+        return Vec2::ZERO;
+    }
+
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    stick / magnitude * scaled.powf(curve)
+}
+
+// Turns the camera with the right stick, speeding up while the stick is held at the edge
+pub fn look(
+    mut analog_input: ResMut<AnalogInput>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut player_query: Query<&mut Transform, With<PlayerController>>,
+    mut camera_query: Query<(&mut CameraController, &mut Transform), Without<PlayerController>>
+) {
+    let delta_time = time.delta_seconds();
+    let gamepad_settings = settings.gamepad;
+
+    if analog_input.look.length() >= 0.95 {
+        analog_input.look_hold_time += delta_time;
+    } else {
+        analog_input.look_hold_time = 0.0;
+    }
+
+    if analog_input.look == Vec2::ZERO {
+        return;
+    }
+
+    let ramp = ((analog_input.look_hold_time - gamepad_settings.acceleration_delay)
+        / gamepad_settings.acceleration_ramp).clamp(0.0, 1.0);
+    let acceleration = 1.0 + (gamepad_settings.aim_acceleration - 1.0) * ramp;
+    // the same per axis scale as the mouse
+    let axis_scale = Vec2::new(settings.sensitivity_x, settings.sensitivity_y);
+    let turn = analog_input.look * axis_scale * gamepad_settings.look_speed * acceleration * delta_time;
+
+    for (mut camera, mut camera_transform) in camera_query.iter_mut() {
+        for mut player_transform in player_query.iter_mut() {
+            let turn = turn * camera.zoom_sensitivity;
+            camera.yaw -= turn.x;
+            camera.pitch += turn.y;
+
+            camera.pitch = camera.pitch.clamp(-MAX_VERTICAL_ANGLE, MAX_VERTICAL_ANGLE);
+
+            camera_transform.rotation = Quat::from_axis_angle(Vec3::X, camera.pitch);
+            player_transform.rotation = Quat::from_axis_angle(Vec3::Y, camera.yaw);
+        }
+    }
+}
+
+// Moves a highlighted focus between menu buttons with the d-pad and presses it with the south button
+pub fn navigate_menu(
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut cursor_events: EventReader<CursorMoved>,
+    mut focus: ResMut<MenuFocus>,
+    mut button_query: Query<(Entity, &GlobalTransform, &mut Interaction, &mut BackgroundColor), With<Button>>
+) {
+    let just_pressed = |button_type| gamepads.iter().any(|gamepad| {
+        gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type))
+    });
+
+    // releases the button pressed last frame
+    if let Some(pressed) = focus.pressed.take() {
+        if let Ok((_, _, mut interaction, _)) = button_query.get_mut(pressed) {
+            if *interaction == Interaction::Pressed {
+                *interaction = Interaction::None;
+            }
+        }
+    }
+
+    // the mouse takes over again once it moves
+    if cursor_events.read().count() > 0 {
+        focus.active = false;
+    }
+
+    let mut buttons: Vec<(Entity, Vec3)> = button_query.iter()
+        .map(|(entity, transform, _, _)| (entity, transform.translation()))
+        .collect();
+    if buttons.is_empty() {
+        return;
+    }
+    buttons.sort_by(|a, b| a.1.y.total_cmp(&b.1.y).then(a.1.x.total_cmp(&b.1.x)));
+
+    let count = buttons.len();
+    focus.index = focus.index.min(count - 1);
+
+    if just_pressed(GamepadButtonType::DPadDown) || just_pressed(GamepadButtonType::DPadRight) {
+        focus.index = if focus.active { (focus.index + 1) % count } else { focus.index };
+        focus.active = true;
+    }
+    if just_pressed(GamepadButtonType::DPadUp) || just_pressed(GamepadButtonType::DPadLeft) {
+        focus.index = if focus.active { (focus.index + count - 1) % count } else { focus.index };
+        focus.active = true;
+    }
+
+    let focused = buttons[focus.index].0;
+
+    if focus.active && just_pressed(GamepadButtonType::South) {
+        if let Ok((_, _, mut interaction, _)) = button_query.get_mut(focused) {
+            *interaction = Interaction::Pressed;
+            focus.pressed = Some(focused);
+        }
+    }
+
+    // only the buttons gaining or losing the focus are recoloured
+    let highlight = focus.active.then_some(focused);
+    if focus.highlighted.map(|(entity, _)| entity) != highlight {
+        if let Some((entity, color)) = focus.highlighted.take() {
+            if let Ok((_, _, _, mut background_color)) = button_query.get_mut(entity) {
+                background_color.0 = color;
+            }
+        }
+        if let Some(entity) = highlight {
+            if let Ok((_, _, _, mut background_color)) = button_query.get_mut(entity) {
+                focus.highlighted = Some((entity, background_color.0));
+                background_color.0 = FOCUS_COLOR;
+            }
+        }
+    }
+}
@@ -3,41 +3,63 @@ use bevy_rapier3d::prelude::*;
 use crate::{
     GameState,
     structs::{
+        BulletImpactEvent,
+        DamageNumber,
+        GameEntity,
+        Dying,
+        Settings,
         TargetController,
+        TargetHitEvent,
+        ShotFiredEvent,
+        ShotHit,
         GunController,
         PlayerController,
         CameraController,
-        StartButton
+        StartButton,
+        InputAction
     }
 };
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH
+};
+
+const RELOAD_ANIMATION_TIME: f32 = 2.5;
+
+// From the view model position to the end of the barrel
+pub const MUZZLE_OFFSET: Vec3 = Vec3::new(0.0, 0.06, -0.55);
 
 // Handles gunplay
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn update(
     mut commands: Commands,
-    mouse_event: Res<ButtonInput<MouseButton>>,
+    actions: Res<ButtonInput<InputAction>>,
     player_query: Query<(Entity, &Children), With<PlayerController>>,
     camera_query: Query<(&GlobalTransform, &Children), (With<CameraController>, Without<PlayerController>)>,    
-    mut gun_query: Query<&mut GunController>,
-    mut enemy_query: Query<&mut TargetController>,
+    mut gun_query: Query<(&mut GunController, &Transform)>,
+    mut enemy_query: Query<(&mut TargetController, &GlobalTransform)>,
     start_query: Query<Entity, (With<StartButton>, Without<PlayerController>)>,
     rapier_context: Res<RapierContext>,
-    mut next_state: ResMut<NextState<GameState>>
+    mut next_state: ResMut<NextState<GameState>>,
+    mut hit_events: EventWriter<TargetHitEvent>,
+    mut shot_events: EventWriter<ShotFiredEvent>,
+    mut impact_events: EventWriter<BulletImpactEvent>
 ) {
     if let Ok((player_entity, player_children)) = player_query.get_single() {
         for child in player_children.iter() {
             if let Ok((camera_transform, camera_children)) = camera_query.get(*child) {
                 for child in camera_children.iter() {
-                    if let Ok(mut gun_controller) = gun_query.get_mut(*child) {
-                        // fires gun when pressing LMB
-                        if mouse_event.just_pressed(MouseButton::Left) {
+                    if let Ok((mut gun_controller, gun_transform)) = gun_query.get_mut(*child) {
+                        // fires gun when pressing the fire binding
+                        if actions.just_pressed(InputAction::Fire) {
                             gun_controller.shooting = true;
                             gun_controller.just_pressed = true;
-                        } else if mouse_event.just_released(MouseButton::Left) {
+                        } else if actions.just_released(InputAction::Fire) {
                             gun_controller.shooting = false;
                         }
 
                         let shooting = gun_controller.shooting;
-                        let just_pressed = gun_controller.just_pressed;                        
+                        let just_pressed = gun_controller.just_pressed;
                         
                         // shoots gun if conditions are met
                         if let Some(bullet_delay) = &mut gun_controller.bullet_delay {
@@ -45,17 +67,23 @@ pub fn update(
                                 // resets bullet delay
                                 bullet_delay.reset();
                                 gun_controller.just_pressed = false;
-                                gun_controller.play_audio = true;
                                 
+                                // scoped weapons are inaccurate until fully scoped in
+                                let mut direction = camera_transform.forward();
+                                if let Some(scope) = gun_controller.scope {
+                                    let spread = scope.unscoped_spread * (1.0 - gun_controller.aim_amount);
+                                    if spread > 0.0 {
+                                        let angle = next_random(&mut gun_controller.spread_seed) * std::f32::consts::TAU;
+                                        let distance = next_random(&mut gun_controller.spread_seed).sqrt() * spread;
+                                        let offset = camera_transform.right() * angle.cos() + camera_transform.up() * angle.sin();
+                                        direction = (direction + offset * distance.tan()).normalize();
+                                    }
+                                }
+
                                 // defines the bullet raycast values
                                 let bullet_ray = Ray3d {
                                     origin: camera_transform.translation(),
-                                    direction: Direction3d::new(Vec3::new(
-                                        camera_transform.forward().x,
-                                        camera_transform.forward().y,
-                                        camera_transform.forward().z,
-                                    ))
-                                    .unwrap(),
+                                    direction: Direction3d::new(direction).unwrap(),
                                 };
                                 // creates a filter for the raycast
                                 let filter = QueryFilter {
@@ -65,28 +93,56 @@ pub fn update(
                                     ..Default::default()
                                 };
                                 // creates the raycast
-                                if let Some((entity, _toi)) = rapier_context.cast_ray(
+                                let hit = rapier_context.cast_ray_and_get_normal(
                                     bullet_ray.origin,
                                     *bullet_ray.direction,
                                     1000.0,
                                     true,
                                     filter,
-                                ) {
-                                    // despawn target if raycast entity id matches target entity id
-                                    if let Ok(mut enemy_controller) = enemy_query.get_mut(entity) {
+                                );
+
+                                let mut shot_hit = ShotHit::Nothing;
+                                if let Some((entity, intersection)) = hit {
+                                    // lets the hit feedback systems know a target was hit
+                                    if let Ok((mut enemy_controller, enemy_transform)) = enemy_query.get_mut(entity) {
+                                        shot_hit = ShotHit::Target {
+                                            entity,
+                                            id: enemy_controller.id
+                                        };
                                         enemy_controller.health -= 1;
-                                        if enemy_controller.health <= 0 {
-                                            commands.entity(entity).despawn();
-                                        }
+                                        hit_events.send(TargetHitEvent {
+                                            target: entity,
+                                            position: enemy_transform.translation(),
+                                            damage: 1,
+                                            killed: enemy_controller.health <= 0
+                                        });
                                     }
                                     // starts game if start button gets shot
                                     else if start_query.get(entity).is_ok() {
+                                        shot_hit = ShotHit::StartButton(entity);
                                         commands.entity(entity).despawn();
                                         next_state.set(GameState::Playing);
                                     }
+                                    // anything else is map geometry
+                                    else {
+                                        shot_hit = ShotHit::Map;
+                                        impact_events.send(BulletImpactEvent {
+                                            position: intersection.point,
+                                            normal: intersection.normal
+                                        });
+                                    }
                                 }
-                            } else {
-                                gun_controller.play_audio = false;
+
+                                // the tracer starts at the barrel and ends where the bullet lands
+                                let muzzle = camera_transform.transform_point(gun_transform.translation + MUZZLE_OFFSET);
+                                let end = hit.map_or(bullet_ray.get_point(100.0), |(_, intersection)| intersection.point);
+                                shot_events.send(ShotFiredEvent {
+                                    origin: muzzle,
+                                    end,
+                                    camera_origin: bullet_ray.origin,
+                                    direction,
+                                    hit: shot_hit
+                                });
                             }
                         }
                     }
@@ -94,4 +150,186 @@ pub fn update(
             }
         }
     }
+}
+
+// A different spread seed for every gun the game spawns, replays record it so they still play
+// out the same
+pub fn random_seed() -> u32 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.subsec_nanos());
+    // xorshift gets stuck at zero
+    nanos.max(1)
+}
+
+// Returns a pseudo random number between 0 and 1, advancing the seed
+pub fn next_random(seed: &mut u32) -> f32 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 17;
+    *seed ^= *seed << 5;
+    (*seed >> 8) as f32 / (1 << 24) as f32
+}
+
+// Aims down the sights while holding the aim binding, narrowing the fov and the mouse sensitivity
+pub fn aim(
+    actions: Res<ButtonInput<InputAction>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut gun_query: Query<&mut GunController>,
+    mut camera_query: Query<&mut CameraController>
+) {
+    let mut zoom = 1.0;
+    let mut aim_amount = 0.0;
+
+    for mut gun_controller in gun_query.iter_mut() {
+        gun_controller.aiming = actions.pressed(InputAction::Aim);
+
+        let target = if gun_controller.aiming { 1.0 } else { 0.0 };
+        let step = time.delta_seconds() / gun_controller.ads_time.max(0.01);
+        gun_controller.aim_amount = if target > gun_controller.aim_amount {
+            (gun_controller.aim_amount + step).min(target)
+        } else {
+            (gun_controller.aim_amount - step).max(target)
+        };
+
+        let full_zoom = gun_controller.scope.map_or(gun_controller.ads_zoom, |scope| scope.zoom);
+        aim_amount = gun_controller.aim_amount;
+        zoom = 1.0 + (full_zoom - 1.0) * aim_amount;
+    }
+
+    for mut camera in camera_query.iter_mut() {
+        // turning speed follows the narrower fov so the mouse feels the same on screen
+        camera.zoom_sensitivity = (1.0 + (settings.zoom_sensitivity - 1.0) * aim_amount) / zoom;
+        // the fov itself is set by settings::update_camera_fov
+        camera.zoom = zoom;
+    }
+}
+
+// Plays the reload animation when pressing the reload binding, it is only for show
+pub fn reload(
+    actions: Res<ButtonInput<InputAction>>,
+    time: Res<Time>,
+    mut gun_query: Query<&mut GunController>
+) {
+    for mut gun_controller in gun_query.iter_mut() {
+        if actions.just_pressed(InputAction::Reload) && gun_controller.reload_timer.is_none() {
+            gun_controller.reload_timer = Some(Timer::from_seconds(RELOAD_ANIMATION_TIME, TimerMode::Once));
+        }
+
+        let mut finished = false;
+        if let Some(reload_timer) = &mut gun_controller.reload_timer {
+            reload_timer.tick(time.delta());
+            finished = reload_timer.finished();
+        }
+        if finished {
+            gun_controller.reload_timer = None;
+        }
+    }
+}
+
+// Takes killed targets out of play and starts shrinking them
+pub fn destroy_targets(
+    mut commands: Commands,
+    mut hit_events: EventReader<TargetHitEvent>,
+    target_query: Query<&Transform, With<TargetController>>
+) {
+    for hit_event in hit_events.read() {
+        if !hit_event.killed {
+            continue;
+        }
+        if let Ok(target_transform) = target_query.get(hit_event.target) {
+            commands.entity(hit_event.target)
+                .remove::<(TargetController, AsyncCollider, Collider, RigidBody)>()
+                .insert(Dying {
+                    timer: Timer::from_seconds(0.15, TimerMode::Once),
+                    start_scale: target_transform.scale
+                });
+        }
+    }
+}
+
+// Shrinks dying targets and despawns them once they are gone
+pub fn animate_dying(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut dying_query: Query<(Entity, &mut Dying, &mut Transform)>
+) {
+    for (entity, mut dying, mut transform) in dying_query.iter_mut() {
+        dying.timer.tick(time.delta());
+        transform.scale = dying.start_scale * (1.0 - dying.timer.fraction());
+        if dying.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// Spawns a floating damage number where a target was hit
+pub fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut hit_events: EventReader<TargetHitEvent>,
+    settings: Res<Settings>
+) {
+    for hit_event in hit_events.read() {
+        if !settings.damage_numbers {
+            continue;
+        }
+        commands.spawn((
+            TextBundle {
+                // shown once the layout knows its size and it can be centered
+                visibility: Visibility::Hidden,
+                ..TextBundle::from_section(
+                    hit_event.damage.to_string(),
+                    TextStyle {
+                        font_size: 24.0,
+                        color: if hit_event.killed { Color::rgb(1.0, 0.3, 0.3) } else { Color::WHITE },
+                        ..Default::default()
+                    }
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                })
+            },
+            DamageNumber {
+                position: hit_event.position,
+                age: 0.0
+            },
+            GameEntity
+        ));
+    }
+}
+
+// Moves damage numbers up from their hit and fades them out
+pub fn update_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<CameraController>>,
+    mut damage_number_query: Query<(Entity, &mut DamageNumber, &Node, &mut Style, &mut Text, &mut Visibility)>
+) {
+    const LIFETIME: f32 = 0.8;
+
+    let Ok((camera, camera_transform)) = camera_query.get_single() else { return };
+
+    for (entity, mut damage_number, node, mut style, mut text, mut visibility) in damage_number_query.iter_mut() {
+        damage_number.age += time.delta_seconds();
+        if damage_number.age >= LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let position = damage_number.position + Vec3::Y * damage_number.age * 0.4;
+        match camera.world_to_viewport(camera_transform, position) {
+            // the text is centered on the hit instead of hanging off it from its corner
+            Some(screen_position) if node.size() != Vec2::ZERO => {
+                let top_left = screen_position - node.size() / 2.0;
+                style.left = Val::Px(top_left.x);
+                style.top = Val::Px(top_left.y);
+                *visibility = Visibility::Inherited;
+            }
+            _ => *visibility = Visibility::Hidden
+        }
+
+        let alpha = 1.0 - damage_number.age / LIFETIME;
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use crate::structs::{
+    HitKind,
+    RunTelemetry
+};
+
+// The same size as the level overview images
+pub const IMAGE_SIZE: u32 = 960;
+// How far one logged point spreads its heat, in metres
+pub const SPLAT_RADIUS: f32 = 1.5;
+// Space left around the map on every side, in metres
+const MARGIN: f32 = 2.0;
+// Height changes bigger than this between two pixels are drawn as a wall
+const WALL_HEIGHT: f32 = 0.5;
+
+const BACKGROUND: [u8; 3] = [24, 24, 28];
+const SAND: [f32; 3] = [205.0, 180.0, 130.0];
+// Cold to hot
+const HEAT_STOPS: [[f32; 3]; 5] = [
+    [0.0, 0.0, 255.0],
+    [0.0, 255.0, 255.0],
+    [0.0, 255.0, 0.0],
+    [255.0, 255.0, 0.0],
+    [255.0, 0.0, 0.0]
+];
+
+// Looks straight down on the map, x goes to the right and z down the image
+pub struct TopDownView {
+    pub min: Vec2,
+    // pixels per metre
+    pub scale: f32,
+    pub size: u32
+}
+
+impl TopDownView {
+    // Fits the points into a square image
+    pub fn fit(points: &[Vec3], size: u32) -> Self {
+        let (mut min, mut max) = (Vec2::ZERO, Vec2::ONE);
+        if let Some(first) = points.first() {
+            (min, max) = points.iter().fold((first.xz(), first.xz()), |(min, max), point| (min.min(point.xz()), max.max(point.xz())));
+        }
+        let extent = (max - min).max_element() + MARGIN * 2.0;
+        Self {
+            min: (min + max) / 2.0 - Vec2::splat(extent / 2.0),
+            scale: size as f32 / extent,
+            size
+        }
+    }
+
+    pub fn to_pixel(&self, point: Vec3) -> Vec2 {
+        (point.xz() - self.min) * self.scale
+    }
+
+    fn pixel_count(&self) -> usize {
+        (self.size * self.size) as usize
+    }
+}
+
+// What a heatmap is drawn from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapLayer {
+    Positions,
+    Shots,
+    Misses,
+    Deaths
+}
+
+impl HeatmapLayer {
+    pub const ALL: [HeatmapLayer; 4] = [HeatmapLayer::Positions, HeatmapLayer::Shots, HeatmapLayer::Misses, HeatmapLayer::Deaths];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeatmapLayer::Positions => "positions",
+            HeatmapLayer::Shots => "shots",
+            HeatmapLayer::Misses => "misses",
+            HeatmapLayer::Deaths => "deaths"
+        }
+    }
+
+    // Shots are drawn where they were fired from and misses where they landed, shooting the
+    // start button isn't part of playing the level and misses that left the map have no spot.
+    // A failed run ends where the player was when the time ran out
+    pub fn points(&self, run: &RunTelemetry) -> Vec<Vec3> {
+        let shots = run.shots.iter().filter(|shot| shot.hit != HitKind::StartButton);
+        match self {
+            HeatmapLayer::Positions => run.positions.clone(),
+            HeatmapLayer::Shots => shots.map(|shot| shot.origin).collect(),
+            HeatmapLayer::Misses => shots
+                .filter(|shot| shot.hit == HitKind::Map)
+                .filter_map(|shot| Some(shot.origin + shot.direction * shot.distance?))
+                .collect(),
+            HeatmapLayer::Deaths => run.positions.last().filter(|_| !run.cleared).copied().into_iter().collect()
+        }
+    }
+}
+
+// The height of the highest surface under every pixel, negative infinity where there is none
+pub fn render_heights(view: &TopDownView, vertices: &[Vec3], triangles: &[[u32; 3]]) -> Vec<f32> {
+    let mut heights = vec![f32::NEG_INFINITY; view.pixel_count()];
+    let last = view.size as f32 - 1.0;
+
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|index| vertices[index as usize]);
+        let [pixel_a, pixel_b, pixel_c] = [a, b, c].map(|vertex| view.to_pixel(vertex));
+        // walls are edge on from above, they show up as steps in the height instead
+        let area = edge(pixel_a, pixel_b, pixel_c);
+        if area.abs() < 1e-6 {
+            continue;
+        }
+
+        let low = pixel_a.min(pixel_b).min(pixel_c).floor().max(Vec2::ZERO);
+        let high = pixel_a.max(pixel_b).max(pixel_c).ceil().min(Vec2::splat(last));
+        for y in low.y as u32..=high.y as u32 {
+            for x in low.x as u32..=high.x as u32 {
+                let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = Vec3::new(
+                    edge(pixel_b, pixel_c, center),
+                    edge(pixel_c, pixel_a, center),
+                    edge(pixel_a, pixel_b, center)
+                ) / area;
+                if weights.min_element() < 0.0 {
+                    continue;
+                }
+
+                let height = weights.dot(Vec3::new(a.y, b.y, c.y));
+                let pixel = &mut heights[(y * view.size + x) as usize];
+                *pixel = pixel.max(height);
+            }
+        }
+    }
+
+    heights
+}
+
+// Draws the layout like the overview images, higher ground is lighter and walls are outlined
+pub fn shade_layout(view: &TopDownView, heights: &[f32]) -> Vec<[u8; 3]> {
+    let (low, high) = heights.iter()
+        .filter(|height| height.is_finite())
+        .fold((f32::MAX, f32::MIN), |(low, high), height| (low.min(*height), high.max(*height)));
+    let range = (high - low).max(0.001);
+    let size = view.size as i32;
+
+    (0..size * size).map(|index| {
+        let height = heights[index as usize];
+        if !height.is_finite() {
+            return BACKGROUND;
+        }
+
+        let (x, y) = (index % size, index / size);
+        let is_edge = [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|(step_x, step_y)| {
+            let (next_x, next_y) = (x + step_x, y + step_y);
+            if next_x < 0 || next_y < 0 || next_x >= size || next_y >= size {
+                return false;
+            }
+            let next = heights[(next_y * size + next_x) as usize];
+            !next.is_finite() || next - height > WALL_HEIGHT
+        });
+
+        let mut brightness = 0.45 + 0.55 * (height - low) / range;
+        if is_edge {
+            brightness *= 0.55;
+        }
+        SAND.map(|channel| (channel * brightness) as u8)
+    }).collect()
+}
+
+// Adds up how many points are close to every pixel, each fading out over the splat radius
+pub fn heat(view: &TopDownView, points: &[Vec3]) -> Vec<f32> {
+    let mut values = vec![0.0; view.pixel_count()];
+    let radius = SPLAT_RADIUS * view.scale;
+    let last = view.size as f32 - 1.0;
+
+    for point in points {
+        let center = view.to_pixel(*point);
+        let low = (center - radius).floor().max(Vec2::ZERO);
+        let high = (center + radius).ceil().min(Vec2::splat(last));
+        if low.x > high.x || low.y > high.y {
+            continue;
+        }
+
+        for y in low.y as u32..=high.y as u32 {
+            for x in low.x as u32..=high.x as u32 {
+                let distance = Vec2::new(x as f32 + 0.5, y as f32 + 0.5).distance(center) / radius;
+                if distance < 1.0 {
+                    values[(y * view.size + x) as usize] += (1.0 - distance * distance).powi(2);
+                }
+            }
+        }
+    }
+
+    values
+}
+
+// Lays the heat over the layout, the hottest spot is red, returns rgb bytes
+pub fn blend(layout: &[[u8; 3]], heat: &[f32]) -> Vec<u8> {
+    let max = heat.iter().copied().fold(0.0, f32::max);
+
+    layout.iter().zip(heat).flat_map(|(colour, value)| {
+        // the square root keeps spots visited once visible next to the busy ones
+        let amount = if max > 0.0 { (value / max).sqrt() } else { 0.0 };
+        let alpha = (amount * 3.0).min(1.0) * 0.8;
+        let hot = heat_colour(amount);
+        [0, 1, 2].map(|channel| (colour[channel] as f32 * (1.0 - alpha) + hot[channel] * alpha) as u8)
+    }).collect()
+}
+
+pub fn encode_png(size: u32, rgb: &[u8]) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, size, size);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|error| format!("Could not write the heatmap: {}", error))?;
+    writer.write_image_data(rgb).map_err(|error| format!("Could not write the heatmap: {}", error))?;
+    writer.finish().map_err(|error| format!("Could not write the heatmap: {}", error))?;
+    Ok(bytes)
+}
+
+fn heat_colour(amount: f32) -> [f32; 3] {
+    let scaled = amount.clamp(0.0, 1.0) * (HEAT_STOPS.len() - 1) as f32;
+    let index = (scaled.floor() as usize).min(HEAT_STOPS.len() - 2);
+    let fraction = scaled - index as f32;
+    [0, 1, 2].map(|channel| HEAT_STOPS[index][channel] + (HEAT_STOPS[index + 1][channel] - HEAT_STOPS[index][channel]) * fraction)
+}
+
+// Twice the signed area of a triangle, positive when it winds counterclockwise
+fn edge(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
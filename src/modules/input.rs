@@ -0,0 +1,155 @@
+use bevy::{
+    input::keyboard::{
+        Key,
+        KeyboardInput
+    },
+    prelude::*
+};
+use crate::structs::{
+    ConfigDirectory,
+    InputAction,
+    InputBinding,
+    InputBindings,
+    KeyNames,
+    RebindState
+};
+use std::fs;
+
+const BINDINGS_FILE: &str = "bindings.ron";
+
+// Loads the key bindings from the config file, writes the defaults if there is none
+pub fn load_bindings(config: &ConfigDirectory) -> InputBindings {
+    let Some(directory) = &config.0 else { return InputBindings::default() };
+    let path = directory.join(BINDINGS_FILE);
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => match ron::from_str::<InputBindings>(&contents) {
+            Ok(mut bindings) => {
+                // actions added after the file was written get their default bindings
+                for (action, defaults) in InputBindings::default().actions {
+                    bindings.actions.entry(action).or_insert(defaults);
+                }
+                bindings
+            }
+            Err(error) => {
+                warn!("Could not parse {}: {}", path.display(), error);
+                InputBindings::default()
+            }
+        },
+        Err(_) => {
+            let bindings = InputBindings::default();
+            save_bindings(config, &bindings);
+            bindings
+        }
+    }
+}
+
+// Writes the key bindings to the config file
+pub fn save_bindings(config: &ConfigDirectory, bindings: &InputBindings) {
+    let Some(directory) = &config.0 else { return };
+    let path = directory.join(BINDINGS_FILE);
+    let _ = fs::create_dir_all(directory);
+
+    match ron::ser::to_string_pretty(bindings, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => {
+            if let Err(error) = fs::write(&path, contents) {
+                warn!("Could not save {}: {}", path.display(), error);
+            }
+        }
+        Err(error) => warn!("Could not serialize key bindings: {}", error)
+    }
+}
+
+// Translates raw keyboard, mouse and gamepad input into input actions
+pub fn update_actions(
+    key_event: Res<ButtonInput<KeyCode>>,
+    mouse_event: Res<ButtonInput<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_event: Res<ButtonInput<GamepadButton>>,
+    bindings: Res<InputBindings>,
+    mut actions: ResMut<ButtonInput<InputAction>>
+) {
+    actions.clear();
+
+    for action in InputAction::ALL.into_iter().chain(InputAction::VIEWER) {
+        let pressed = bindings.get(action).iter().any(|binding| match binding {
+            InputBinding::Key(key) => key_event.pressed(*key),
+            InputBinding::Mouse(button) => mouse_event.pressed(*button),
+            InputBinding::Gamepad(button_type) => gamepads.iter().any(|gamepad| {
+                gamepad_event.pressed(GamepadButton::new(gamepad, *button_type))
+            })
+        });
+
+        if pressed {
+            actions.press(action);
+        } else {
+            actions.release(action);
+        }
+    }
+}
+
+// Remembers the character the layout puts on a key, so AZERTY players see Z where W is
+pub fn learn_key_names(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut key_names: ResMut<KeyNames>
+) {
+    for event in keyboard_events.read() {
+        let Key::Character(character) = &event.logical_key else { continue };
+        let name = character.to_uppercase();
+        if key_names.0.get(&event.key_code) != Some(&name) {
+            key_names.0.insert(event.key_code, name);
+        }
+    }
+}
+
+// Binds the next pressed key, mouse or gamepad button to the action being rebound. Clicking a
+// menu button cancels it instead, see menu_interactions
+pub fn capture_rebind(
+    key_event: Res<ButtonInput<KeyCode>>,
+    mouse_event: Res<ButtonInput<MouseButton>>,
+    gamepad_event: Res<ButtonInput<GamepadButton>>,
+    mut rebind_state: ResMut<RebindState>,
+    mut bindings: ResMut<InputBindings>,
+    config: Res<ConfigDirectory>
+) {
+    let Some(action) = rebind_state.action else { return };
+
+    // skips the frame of the click that started the rebind
+    if !rebind_state.armed {
+        rebind_state.armed = true;
+        return;
+    }
+
+    let binding = key_event.get_just_pressed().next().map(|key| InputBinding::Key(*key))
+        .or_else(|| mouse_event.get_just_pressed().next().map(|button| InputBinding::Mouse(*button)))
+        .or_else(|| gamepad_event.get_just_pressed().next().map(|button| InputBinding::Gamepad(button.button_type)));
+
+    if let Some(binding) = binding {
+        match bindings.rebind(action, binding) {
+            Ok(()) => save_bindings(&config, &bindings),
+            Err(error) => warn!("Could not rebind {}: {}", action.label(), error)
+        }
+        rebind_state.action = None;
+    }
+}
+
+// Returns a readable name for a binding, keys use the layout's name once it is known
+pub fn binding_name(binding: &InputBinding, key_names: &KeyNames) -> String {
+    match binding {
+        InputBinding::Key(key) if key_names.0.contains_key(key) => key_names.0[key].clone(),
+        InputBinding::Key(key) => {
+            let name = format!("{:?}", key);
+            name.strip_prefix("Key")
+                .or_else(|| name.strip_prefix("Digit"))
+                .unwrap_or(&name)
+                .to_uppercase()
+        }
+        InputBinding::Mouse(MouseButton::Left) => "MOUSE 1".to_string(),
+        InputBinding::Mouse(MouseButton::Right) => "MOUSE 2".to_string(),
+        InputBinding::Mouse(MouseButton::Middle) => "MOUSE 3".to_string(),
+        InputBinding::Mouse(MouseButton::Back) => "MOUSE 4".to_string(),
+        InputBinding::Mouse(MouseButton::Forward) => "MOUSE 5".to_string(),
+        InputBinding::Mouse(MouseButton::Other(button)) => format!("MOUSE {}", button),
+        InputBinding::Gamepad(button_type) => format!("PAD {:?}", button_type).to_uppercase()
+    }
+}
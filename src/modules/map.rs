@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use gltf::{
+    buffer::Source,
+    mesh::Mode,
+    Gltf
+};
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+pub const MAP_FILE: &str = "de_dust2.glb";
+
+// Where the map file is, found the same way bevy finds the assets folder
+pub fn map_path() -> PathBuf {
+    bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(MAP_FILE)
+}
+
+// The map is turned around once it has loaded, see entities::rotate_map
+pub fn map_transform() -> Transform {
+    Transform::from_rotation(Quat::from_rotation_y(std::f32::consts::PI))
+}
+
+// Reads every triangle of a glTF binary, in the space of its scene root
+pub fn load_map_triangles(path: &Path) -> Result<(Vec<Vec3>, Vec<[u32; 3]>), String> {
+    let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+    let gltf = Gltf::from_slice(&bytes).map_err(|error| format!("Could not parse {}: {}", path.display(), error))?;
+    let blob = gltf.blob.as_deref();
+
+    let scene = gltf.default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or_else(|| format!("{} has no scene", path.display()))?;
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for node in scene.nodes() {
+        collect_triangles(&node, Mat4::IDENTITY, blob, &mut vertices, &mut indices);
+    }
+    Ok((vertices, indices))
+}
+
+// Builds one triangle mesh collider out of the whole map
+pub fn load_map_collider(path: &Path) -> Result<Collider, String> {
+    let (vertices, indices) = load_map_triangles(path)?;
+    if indices.is_empty() {
+        return Err(format!("{} has no triangles", path.display()));
+    }
+    Ok(Collider::trimesh(vertices, indices))
+}
+
+fn collect_triangles(
+    node: &gltf::Node,
+    parent_transform: Mat4,
+    blob: Option<&[u8]>,
+    vertices: &mut Vec<Vec3>,
+    indices: &mut Vec<[u32; 3]>
+) {
+    let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                continue;
+            }
+            // glb files keep their data in the binary chunk
+            let reader = primitive.reader(|buffer| match buffer.source() {
+                Source::Bin => blob,
+                Source::Uri(_) => None
+            });
+            let Some(positions) = reader.read_positions() else { continue };
+
+            let start = vertices.len() as u32;
+            vertices.extend(positions.map(|position| transform.transform_point3(Vec3::from(position))));
+            let count = vertices.len() as u32 - start;
+
+            match reader.read_indices() {
+                Some(read_indices) => {
+                    let primitive_indices: Vec<u32> = read_indices.into_u32().collect();
+                    indices.extend(primitive_indices.chunks_exact(3).map(|triangle| {
+                        [start + triangle[0], start + triangle[1], start + triangle[2]]
+                    }));
+                }
+                None => indices.extend((0..count / 3).map(|triangle| {
+                    [start + triangle * 3, start + triangle * 3 + 1, start + triangle * 3 + 2]
+                }))
+            }
+        }
+    }
+
+    for child in node.children() {
+        collect_triangles(&child, transform, blob, vertices, indices);
+    }
+}
@@ -4,12 +4,43 @@ use crate::{
     structs::{
         MenuButtonAction,
         MenuEntity,
-        LastState
-    }
+        LastState,
+        Settings,
+        InputAction,
+        InputBindings,
+        RebindState,
+        BindingText,
+        ConfigDirectory,
+        KeyNames,
+        SensitivityImport,
+        SettingField,
+        SettingText,
+        ShareCodeInput,
+        ShareCodeText,
+        Slider,
+        SliderFill
+    },
+    crosshair::{
+        share_code_label,
+        spawn_crosshair
+    },
+    input::{
+        binding_name,
+        save_bindings
+    },
+    settings::{
+        adjust_setting,
+        convert_sensitivity,
+        set_slider_value,
+        setting_label,
+        slider_value
+    },
+    MenuState
 };
 use bevy::{
     prelude::*,
     app::AppExit,
+    ui::RelativeCursorPosition,
     window::CursorGrabMode
 };
 
@@ -18,7 +49,13 @@ pub fn setup_main_menu(
     mut commands: Commands
 ) { 
     commands.spawn(Camera2dBundle::default()).insert(MenuEntity);
-    
+
+    spawn_main_menu(&mut commands);
+}
+
+fn spawn_main_menu(
+    commands: &mut Commands
+) {
     let button_style: Style = Style {
         width: Val::Px(250.0),
         height: Val::Px(65.0),
@@ -79,6 +116,8 @@ pub fn setup_main_menu(
             ).insert(MenuEntity);
         });
         
+        spawn_button(parent, &button_style, MenuButtonAction::Settings, "SETTINGS", 40.0);
+
         parent.spawn((
             ButtonBundle {
                 style: button_style.clone(),
@@ -103,6 +142,7 @@ pub fn setup_main_menu(
 }
 
 // Handles menu interactions
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn menu_interactions(
     interaction_query: Query<
         (&Interaction, &MenuButtonAction),
@@ -111,10 +151,20 @@ pub fn menu_interactions(
     mut app_exit_event: ResMut<Events<AppExit>>,
     mut new_game_state: ResMut<NextState<GameState>>,
     mut new_level_state: ResMut<NextState<LevelState>>,
-    last_state: Res<LastState>
+    last_state: Res<LastState>,
+    mut settings: ResMut<Settings>,
+    menu_state: Res<State<MenuState>>,
+    mut new_menu_state: ResMut<NextState<MenuState>>,
+    mut rebind_state: ResMut<RebindState>,
+    mut bindings: ResMut<InputBindings>,
+    config: Res<ConfigDirectory>,
+    mut import: ResMut<SensitivityImport>,
+    mut share_code: ResMut<ShareCodeInput>
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
+            // the click ends a rebind in progress instead of being bound to it
+            let rebinding = rebind_state.action.take();
             match menu_button_action {
                 MenuButtonAction::Play => {
                     new_level_state.set(LevelState::Level1);
@@ -130,6 +180,65 @@ pub fn menu_interactions(
                 MenuButtonAction::Quit => {
                     app_exit_event.send(AppExit);
                 }
+                MenuButtonAction::Settings => {
+                    new_menu_state.set(MenuState::Settings);
+                }
+                MenuButtonAction::Controls => {
+                    new_menu_state.set(MenuState::Controls);
+                }
+                MenuButtonAction::Back => {
+                    share_code.typing = false;
+                    // sub menus of the settings go back to the settings
+                    match menu_state.get() {
+                        MenuState::Controls
+                        | MenuState::Sensitivity
+                        | MenuState::Crosshair
+                        | MenuState::Audio
+                        | MenuState::Weapon => new_menu_state.set(MenuState::Settings),
+                        _ => new_menu_state.set(MenuState::Root)
+                    }
+                }
+                // clicking the binding that is waiting for a key again cancels it
+                MenuButtonAction::Rebind(action) if rebinding != Some(*action) => {
+                    rebind_state.action = Some(*action);
+                    rebind_state.armed = false;
+                }
+                MenuButtonAction::Rebind(_) => {}
+                MenuButtonAction::DefaultBindings => {
+                    *bindings = InputBindings::default();
+                    save_bindings(&config, &bindings);
+                }
+                MenuButtonAction::LeftHandedBindings => {
+                    *bindings = InputBindings::left_handed();
+                    save_bindings(&config, &bindings);
+                }
+                MenuButtonAction::Sensitivity => {
+                    new_menu_state.set(MenuState::Sensitivity);
+                }
+                MenuButtonAction::Adjust(field, step) => {
+                    adjust_setting(&mut settings, &mut import, *field, *step);
+                }
+                MenuButtonAction::CycleImportGame => {
+                    import.game = import.game.next();
+                    import.sensitivity = import.game.default_sensitivity();
+                }
+                MenuButtonAction::ApplyImport => {
+                    settings.sensitivity = convert_sensitivity(&import, settings.mouse_dpi).clamp(0.05, 10.0);
+                }
+                MenuButtonAction::Crosshair => {
+                    new_menu_state.set(MenuState::Crosshair);
+                }
+                MenuButtonAction::Audio => {
+                    new_menu_state.set(MenuState::Audio);
+                }
+                MenuButtonAction::Weapon => {
+                    new_menu_state.set(MenuState::Weapon);
+                }
+                MenuButtonAction::EnterShareCode => {
+                    share_code.code.clear();
+                    share_code.message = None;
+                    share_code.typing = true;
+                }
             }
         }
     }
@@ -140,11 +249,17 @@ pub fn setup_pause_menu(
     mut commands: Commands,
     mut window: Query<&mut Window>
 ) {
-    let mut window = window.get_single_mut().unwrap();
+    if let Ok(mut window) = window.get_single_mut() {
+        window.cursor.visible = true;
+        window.cursor.grab_mode = CursorGrabMode::None;
+    }
 
-    window.cursor.visible = true;
-    window.cursor.grab_mode = CursorGrabMode::None;
+    spawn_pause_menu(&mut commands);
+}
 
+fn spawn_pause_menu(
+    commands: &mut Commands
+) {
     let button_style: Style = Style {
         width: Val::Px(250.0),
         height: Val::Px(65.0),
@@ -189,6 +304,8 @@ pub fn setup_pause_menu(
             ).insert(MenuEntity);
         });
         
+        spawn_button(parent, &button_style, MenuButtonAction::Settings, "SETTINGS", 40.0);
+
         parent.spawn((
             ButtonBundle {
                 style: button_style.clone(),
@@ -210,4 +327,815 @@ pub fn setup_pause_menu(
             ).insert(MenuEntity);
         });
     });
+}
+
+// Sets up the settings menu
+pub fn setup_settings_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    import: Res<SensitivityImport>
+) {
+    let button_style: Style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    commands.spawn(
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).insert(MenuEntity)
+    .with_children(|parent| {
+        spawn_heading(parent, "SETTINGS");
+        spawn_value_row(parent, &settings, &import, "MOVEMENT", SettingField::Movement, 1.0);
+        spawn_value_row(parent, &settings, &import, "WINDOW MODE", SettingField::WindowMode, 1.0);
+        spawn_value_row(parent, &settings, &import, "RESOLUTION", SettingField::Resolution, 1.0);
+        spawn_value_row(parent, &settings, &import, "VSYNC", SettingField::Vsync, 1.0);
+        spawn_value_row(parent, &settings, &import, "ANTI-ALIASING", SettingField::Msaa, 1.0);
+        spawn_value_row(parent, &settings, &import, "FIELD OF VIEW", SettingField::Fov, 1.0);
+        spawn_value_row(parent, &settings, &import, "BEST RUN GHOST", SettingField::Ghost, 1.0);
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            spawn_button(parent, &button_style, MenuButtonAction::Controls, "CONTROLS", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Sensitivity, "SENSITIVITY", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Crosshair, "CROSSHAIR", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Audio, "AUDIO", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Weapon, "WEAPON", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Back, "BACK", 30.0);
+        });
+    });
+}
+
+// Sets up the controls menu for rebinding input actions
+pub fn setup_controls_menu(
+    mut commands: Commands,
+    bindings: Res<InputBindings>,
+    key_names: Res<KeyNames>
+) {
+    let button_style: Style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    let binding_style: Style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(34.0),
+        margin: UiRect::all(Val::Px(4.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    commands.spawn(
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).insert(MenuEntity)
+    .with_children(|parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                "CONTROLS",
+                TextStyle {
+                    font_size: 60.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                }
+            )
+            .with_style(Style {
+                margin: UiRect::all(Val::Px(20.0)),
+                ..Default::default()
+            })
+        ).insert(MenuEntity);
+
+        // one row per action, clicking the binding waits for a new key. The replay viewer's
+        // actions get a column of their own
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    column_gap: Val::Px(40.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            for (heading, actions) in [("GAME", &InputAction::ALL[..]), ("REPLAY VIEWER", &InputAction::VIEWER[..])] {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    MenuEntity
+                ))
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            heading,
+                            TextStyle {
+                                font_size: 30.0,
+                                color: Color::WHITE,
+                                ..Default::default()
+                            }
+                        )
+                    ).insert(MenuEntity);
+                    for action in actions {
+                        spawn_binding_row(parent, &binding_style, &bindings, &key_names, *action);
+                    }
+                });
+            }
+        });
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            spawn_button(parent, &button_style, MenuButtonAction::DefaultBindings, "DEFAULT", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::LeftHandedBindings, "LEFT-HANDED", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Back, "BACK", 30.0);
+        });
+    });
+}
+
+// Sets up the sensitivity menu with the import tool for other games
+pub fn setup_sensitivity_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    import: Res<SensitivityImport>
+) {
+    let button_style: Style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    commands.spawn(
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).insert(MenuEntity)
+    .with_children(|parent| {
+        spawn_heading(parent, "SENSITIVITY");
+        spawn_value_row(parent, &settings, &import, "SENSITIVITY", SettingField::Sensitivity, 0.05);
+        spawn_value_row(parent, &settings, &import, "X SCALE", SettingField::SensitivityX, 0.05);
+        spawn_value_row(parent, &settings, &import, "Y SCALE", SettingField::SensitivityY, 0.05);
+        spawn_value_row(parent, &settings, &import, "MOUSE DPI", SettingField::MouseDpi, 50.0);
+
+        // converts a sensitivity from another game into this one
+        spawn_heading(parent, "IMPORT");
+        spawn_button(parent, &button_style, MenuButtonAction::CycleImportGame, "CHANGE GAME", 30.0);
+        spawn_value_row(parent, &settings, &import, "GAME SENS", SettingField::ImportSensitivity, 1.0);
+        spawn_value_row(parent, &settings, &import, "GAME DPI", SettingField::ImportDpi, 50.0);
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            spawn_button(parent, &button_style, MenuButtonAction::ApplyImport, "APPLY IMPORT", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Back, "BACK", 30.0);
+        });
+    });
+}
+
+// Sets up the crosshair menu with a live preview and share code import
+pub fn setup_crosshair_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    import: Res<SensitivityImport>,
+    share_code: Res<ShareCodeInput>
+) {
+    let button_style: Style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    commands.spawn(
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).insert(MenuEntity)
+    .with_children(|parent| {
+        spawn_heading(parent, "CROSSHAIR");
+
+        // preview
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(120.0),
+                    height: Val::Px(120.0),
+                    margin: UiRect::all(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                background_color: Color::rgb(0.45, 0.4, 0.3).into(),
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(spawn_crosshair);
+
+        spawn_value_row(parent, &settings, &import, "GAP", SettingField::CrosshairGap, 1.0);
+        spawn_value_row(parent, &settings, &import, "LENGTH", SettingField::CrosshairLength, 1.0);
+        spawn_value_row(parent, &settings, &import, "THICKNESS", SettingField::CrosshairThickness, 1.0);
+        spawn_value_row(parent, &settings, &import, "OUTLINE", SettingField::CrosshairOutline, 1.0);
+        spawn_value_row(parent, &settings, &import, "OUTLINE WIDTH", SettingField::CrosshairOutlineThickness, 0.5);
+        spawn_value_row(parent, &settings, &import, "COLOR", SettingField::CrosshairColor, 1.0);
+        spawn_value_row(parent, &settings, &import, "ALPHA", SettingField::CrosshairAlpha, 0.05);
+        spawn_value_row(parent, &settings, &import, "CENTER DOT", SettingField::CrosshairDot, 1.0);
+        spawn_value_row(parent, &settings, &import, "DYNAMIC", SettingField::CrosshairDynamic, 1.0);
+        spawn_value_row(parent, &settings, &import, "HIT MARKER", SettingField::CrosshairHitMarker, 1.0);
+        spawn_value_row(parent, &settings, &import, "DAMAGE NUMBERS", SettingField::DamageNumbers, 1.0);
+
+        // imports a counter-strike crosshair share code typed by the player
+        parent.spawn((
+            TextBundle::from_section(
+                share_code_label(&share_code),
+                TextStyle {
+                    font_size: 26.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                }
+            )
+            .with_style(Style {
+                margin: UiRect::top(Val::Px(15.0)),
+                ..Default::default()
+            }),
+            ShareCodeText,
+            MenuEntity
+        ));
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    margin: UiRect::top(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            spawn_button(parent, &button_style, MenuButtonAction::EnterShareCode, "SHARE CODE", 30.0);
+            spawn_button(parent, &button_style, MenuButtonAction::Back, "BACK", 30.0);
+        });
+    });
+}
+
+// Sets up the audio menu with a slider for every bus
+pub fn setup_audio_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    import: Res<SensitivityImport>
+) {
+    let button_style: Style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    commands.spawn(
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).insert(MenuEntity)
+    .with_children(|parent| {
+        spawn_heading(parent, "AUDIO");
+        spawn_slider_row(parent, &settings, &import, "MASTER", SettingField::MasterVolume);
+        spawn_slider_row(parent, &settings, &import, "AMBIENCE", SettingField::AmbienceVolume);
+        spawn_slider_row(parent, &settings, &import, "WEAPONS", SettingField::WeaponVolume);
+        spawn_slider_row(parent, &settings, &import, "INTERFACE", SettingField::UiVolume);
+        spawn_value_row(parent, &settings, &import, "MUTE IN BACKGROUND", SettingField::MuteUnfocused, 1.0);
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            spawn_button(parent, &button_style, MenuButtonAction::Back, "BACK", 30.0);
+        });
+    });
+}
+
+// Sets up the weapon menu for the view model and shooting effects
+pub fn setup_weapon_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    import: Res<SensitivityImport>
+) {
+    let button_style: Style = Style {
+        width: Val::Px(250.0),
+        height: Val::Px(45.0),
+        margin: UiRect::all(Val::Px(10.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    commands.spawn(
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    ).insert(MenuEntity)
+    .with_children(|parent| {
+        spawn_heading(parent, "WEAPON");
+        spawn_value_row(parent, &settings, &import, "VIEWMODEL FOV", SettingField::ViewmodelFov, 1.0);
+        spawn_value_row(parent, &settings, &import, "VIEWMODEL X", SettingField::ViewmodelX, 0.01);
+        spawn_value_row(parent, &settings, &import, "VIEWMODEL Y", SettingField::ViewmodelY, 0.01);
+        spawn_value_row(parent, &settings, &import, "VIEWMODEL Z", SettingField::ViewmodelZ, 0.01);
+        spawn_value_row(parent, &settings, &import, "VIEWMODEL HAND", SettingField::ViewmodelHand, 1.0);
+        spawn_value_row(parent, &settings, &import, "ZOOM SENSITIVITY", SettingField::ZoomSensitivity, 0.05);
+        spawn_value_row(parent, &settings, &import, "BULLET HOLES", SettingField::BulletDecals, 1.0);
+        spawn_value_row(parent, &settings, &import, "TRACERS", SettingField::Tracers, 1.0);
+        spawn_value_row(parent, &settings, &import, "MUZZLE FLASH", SettingField::MuzzleFlash, 1.0);
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    margin: UiRect::top(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            spawn_button(parent, &button_style, MenuButtonAction::Back, "BACK", 30.0);
+        });
+    });
+}
+
+// Sets a slider setting from where the slider is being dragged
+pub fn drag_sliders(
+    slider_query: Query<(&Interaction, &RelativeCursorPosition, &Slider)>,
+    mut settings: ResMut<Settings>
+) {
+    for (interaction, relative_cursor_position, Slider(field)) in slider_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(position) = relative_cursor_position.normalized {
+            if slider_value(&settings, *field) != Some(position.x.clamp(0.0, 1.0)) {
+                set_slider_value(&mut settings, *field, position.x);
+            }
+        }
+    }
+}
+
+// Keeps the filled part of the sliders in sync with the settings
+pub fn update_sliders(
+    settings: Res<Settings>,
+    mut slider_fill_query: Query<(&mut Style, &SliderFill)>
+) {
+    if settings.is_changed() {
+        for (mut style, SliderFill(field)) in slider_fill_query.iter_mut() {
+            style.width = Val::Percent(slider_value(&settings, *field).unwrap_or(0.0) * 100.0);
+        }
+    }
+}
+
+// Respawns the main or pause menu when coming back from a sub menu
+pub fn return_to_root_menu(
+    mut commands: Commands,
+    game_state: Res<State<GameState>>
+) {
+    match game_state.get() {
+        GameState::MainMenu => spawn_main_menu(&mut commands),
+        GameState::PauseMenu => spawn_pause_menu(&mut commands),
+        _ => {}
+    }
+}
+
+// Keeps the binding buttons in sync with the bindings
+pub fn update_binding_text(
+    bindings: Res<InputBindings>,
+    rebind_state: Res<RebindState>,
+    key_names: Res<KeyNames>,
+    mut binding_text_query: Query<(&mut Text, &BindingText)>
+) {
+    if bindings.is_changed() || rebind_state.is_changed() || key_names.is_changed() {
+        for (mut binding_text, BindingText(action)) in binding_text_query.iter_mut() {
+            binding_text.sections[0].value = if rebind_state.action == Some(*action) {
+                "PRESS A KEY...".to_string()
+            } else {
+                bindings_label(&bindings, &key_names, *action)
+            };
+        }
+    }
+}
+
+// Keeps the values next to the +/- buttons in sync with the settings
+pub fn update_setting_text(
+    settings: Res<Settings>,
+    import: Res<SensitivityImport>,
+    mut setting_text_query: Query<(&mut Text, &SettingText)>
+) {
+    if settings.is_changed() || import.is_changed() {
+        for (mut setting_text, SettingText(field)) in setting_text_query.iter_mut() {
+            setting_text.sections[0].value = setting_label(&settings, &import, *field);
+        }
+    }
+}
+
+fn bindings_label(bindings: &InputBindings, key_names: &KeyNames, action: InputAction) -> String {
+    let names: Vec<String> = bindings.get(action).iter().map(|binding| binding_name(binding, key_names)).collect();
+    if names.is_empty() {
+        "UNBOUND".to_string()
+    } else {
+        names.join(" / ")
+    }
+}
+
+// Despawns the ui of the current menu screen while keeping the menu camera
+pub fn despawn_menu_nodes(
+    mut commands: Commands,
+    menu_node_query: Query<Entity, (With<MenuEntity>, With<Node>)>
+) {
+    for menu_node in menu_node_query.iter() {
+        commands.entity(menu_node).despawn();
+    }
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    button_style: &Style,
+    action: MenuButtonAction,
+    label: &str,
+    font_size: f32
+) {
+    parent.spawn((
+        ButtonBundle {
+            style: button_style.clone(),
+            ..Default::default()
+        },
+        action,
+        MenuEntity
+    ))
+    .with_children(|parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size,
+                    color: Color::BLACK,
+                    ..Default::default()
+                }
+            )
+        ).insert(MenuEntity);
+    });
+}
+
+// Spawns an action's label with a button showing its bindings
+fn spawn_binding_row(
+    parent: &mut ChildBuilder,
+    binding_style: &Style,
+    bindings: &InputBindings,
+    key_names: &KeyNames,
+    action: InputAction
+) {
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        MenuEntity
+    ))
+    .with_children(|parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                action.label(),
+                TextStyle {
+                    font_size: 26.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                }
+            )
+            .with_style(Style {
+                width: Val::Px(200.0),
+                ..Default::default()
+            })
+        ).insert(MenuEntity);
+
+        parent.spawn((
+            ButtonBundle {
+                style: binding_style.clone(),
+                ..Default::default()
+            },
+            MenuButtonAction::Rebind(action),
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    bindings_label(bindings, key_names, action),
+                    TextStyle {
+                        font_size: 22.0,
+                        color: Color::BLACK,
+                        ..Default::default()
+                    }
+                ),
+                BindingText(action),
+                MenuEntity
+            ));
+        });
+    });
+}
+
+fn spawn_heading(
+    parent: &mut ChildBuilder,
+    label: &str
+) {
+    parent.spawn(
+        TextBundle::from_section(
+            label,
+            TextStyle {
+                font_size: 50.0,
+                color: Color::WHITE,
+                ..Default::default()
+            }
+        )
+        .with_style(Style {
+            margin: UiRect::all(Val::Px(15.0)),
+            ..Default::default()
+        })
+    ).insert(MenuEntity);
+}
+
+// Spawns a setting label with -/+ buttons around its value
+fn spawn_value_row(
+    parent: &mut ChildBuilder,
+    settings: &Settings,
+    import: &SensitivityImport,
+    label: &str,
+    field: SettingField,
+    step: f32
+) {
+    let small_button_style: Style = Style {
+        width: Val::Px(45.0),
+        height: Val::Px(38.0),
+        margin: UiRect::all(Val::Px(4.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        MenuEntity
+    ))
+    .with_children(|parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 26.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                }
+            )
+            .with_style(Style {
+                width: Val::Px(220.0),
+                ..Default::default()
+            })
+        ).insert(MenuEntity);
+
+        spawn_button(parent, &small_button_style, MenuButtonAction::Adjust(field, -step), "-", 30.0);
+
+        parent.spawn((
+            TextBundle::from_section(
+                setting_label(settings, import, field),
+                TextStyle {
+                    font_size: 26.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                }
+            )
+            .with_style(Style {
+                width: Val::Px(420.0),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            }),
+            SettingText(field),
+            MenuEntity
+        ));
+
+        spawn_button(parent, &small_button_style, MenuButtonAction::Adjust(field, step), "+", 30.0);
+    });
+}
+
+// Spawns a setting label with a draggable slider between -/+ buttons
+fn spawn_slider_row(
+    parent: &mut ChildBuilder,
+    settings: &Settings,
+    import: &SensitivityImport,
+    label: &str,
+    field: SettingField
+) {
+    let small_button_style: Style = Style {
+        width: Val::Px(45.0),
+        height: Val::Px(38.0),
+        margin: UiRect::all(Val::Px(4.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        MenuEntity
+    ))
+    .with_children(|parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 26.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                }
+            )
+            .with_style(Style {
+                width: Val::Px(220.0),
+                ..Default::default()
+            })
+        ).insert(MenuEntity);
+
+        spawn_button(parent, &small_button_style, MenuButtonAction::Adjust(field, -0.05), "-", 30.0);
+
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(300.0),
+                    height: Val::Px(16.0),
+                    margin: UiRect::horizontal(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                background_color: Color::rgb(0.3, 0.3, 0.3).into(),
+                ..Default::default()
+            },
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            Slider(field),
+            MenuEntity
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(slider_value(settings, field).unwrap_or(0.0) * 100.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    background_color: Color::WHITE.into(),
+                    ..Default::default()
+                },
+                SliderFill(field),
+                MenuEntity
+            ));
+        });
+
+        spawn_button(parent, &small_button_style, MenuButtonAction::Adjust(field, 0.05), "+", 30.0);
+
+        parent.spawn((
+            TextBundle::from_section(
+                setting_label(settings, import, field),
+                TextStyle {
+                    font_size: 26.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                }
+            )
+            .with_style(Style {
+                width: Val::Px(100.0),
+                ..Default::default()
+            }),
+            SettingText(field),
+            MenuEntity
+        ));
+    });
 }
\ No newline at end of file
@@ -3,4 +3,17 @@ pub mod controls;
 pub mod audio;
 pub mod gunplay;
 pub mod structs;
-pub mod menu;
\ No newline at end of file
+pub mod menu;
+pub mod input;
+pub mod gamepad;
+pub mod settings;
+pub mod crosshair;
+pub mod effects;
+pub mod viewmodel;
+pub mod map;
+pub mod navigation;
+pub mod validation;
+pub mod replay;
+pub mod viewer;
+pub mod telemetry;
+pub mod heatmap;
\ No newline at end of file
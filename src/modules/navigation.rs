@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::map::{
+    load_map_collider,
+    map_path,
+    map_transform
+};
+use std::{
+    cmp::Reverse,
+    collections::{
+        BinaryHeap,
+        HashMap,
+        HashSet
+    }
+};
+
+// Size of one square of the walkable grid
+pub const CELL: f32 = 0.5;
+// Highest step the player gets up without jumping
+pub const STEP_HEIGHT: f32 = 0.3;
+// Highest ledge the player is expected to drop down from
+pub const DROP_HEIGHT: f32 = 1.5;
+pub const HEADROOM: f32 = 0.8;
+// The capsule bottom sits 0.1 under the player, the camera 0.65 above it
+pub const FEET: f32 = 0.1;
+pub const EYE_HEIGHT: f32 = 0.75;
+// Targets further away than this are too small to count as seen
+pub const VIEW_RANGE: f32 = 30.0;
+
+// Ray casts against the map collision alone, without a physics world
+pub struct MapShape {
+    pub collider: Collider,
+    pub transform: Transform
+}
+
+impl MapShape {
+    pub fn new(collider: Collider) -> Self {
+        Self {
+            collider,
+            transform: map_transform()
+        }
+    }
+
+    // Reads the map collision straight from the map file
+    pub fn load() -> Result<Self, String> {
+        Ok(Self::new(load_map_collider(&map_path())?))
+    }
+
+    // Returns the distance and surface normal of the first hit
+    pub fn ray(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+        self.collider.cast_ray_and_get_normal(
+            self.transform.translation,
+            self.transform.rotation,
+            origin,
+            direction,
+            max_distance,
+            true
+        ).map(|hit| (hit.toi, hit.normal))
+    }
+
+    pub fn is_clear(&self, from: Vec3, to: Vec3) -> bool {
+        let offset = to - from;
+        let distance = offset.length();
+        distance < 0.001 || self.ray(from, offset / distance, distance).is_none()
+    }
+
+    // Whether an eye sees a sphere, the last bit of the line is the sphere itself
+    pub fn can_see(&self, eye: Vec3, position: Vec3, radius: f32) -> bool {
+        eye.distance(position) < VIEW_RANGE && self.is_clear(eye, position + (eye - position).normalize_or_zero() * radius)
+    }
+
+    pub fn distance_to_surface(&self, point: Vec3) -> f32 {
+        let projection = self.collider.project_point(self.transform.translation, self.transform.rotation, point, false);
+        projection.point.distance(point)
+    }
+
+    // Finds a floor with room to stand under a point, no further down than the max drop
+    pub fn floor_below(&self, point: Vec3, max_drop: f32) -> Option<f32> {
+        let (distance, normal) = self.ray(point, -Vec3::Y, max_drop)?;
+        let floor = point.y - distance;
+        let has_room = self.is_clear(Vec3::new(point.x, floor + 0.05, point.z), Vec3::new(point.x, floor + HEADROOM, point.z));
+        (normal.y.abs() > 0.6 && has_room).then_some(floor)
+    }
+}
+
+// Floor points a player can stand on with the moves between them
+pub struct NavGrid {
+    pub points: Vec<Vec3>,
+    pub links: Vec<Vec<usize>>,
+    pub cells: HashMap<(i32, i32), Vec<usize>>
+}
+
+impl NavGrid {
+    pub fn empty() -> Self {
+        Self {
+            points: Vec::new(),
+            links: Vec::new(),
+            cells: HashMap::new()
+        }
+    }
+
+    // Flood fills the floor outwards from the spawn, so only places the player can get to are in it
+    pub fn build(map: &MapShape, spawn_point: Vec3) -> Self {
+        let mut grid = NavGrid::empty();
+        let Some(start_floor) = map.floor_below(spawn_point, 3.0) else { return grid };
+        let start = grid.add_point(cell_of(spawn_point), start_floor);
+        let mut open = vec![start];
+
+        while let Some(index) = open.pop() {
+            let point = grid.points[index];
+            let (cell_x, cell_z) = cell_of(point);
+
+            for (step_x, step_z) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                let cell = (cell_x + step_x, cell_z + step_z);
+                let center = cell_center(cell);
+
+                // the way over has to be free at knee and head height
+                let knee = Vec3::Y * (STEP_HEIGHT + 0.05);
+                let head = Vec3::Y * (HEADROOM - 0.1);
+                let next = Vec3::new(center.x, point.y, center.y);
+                if !map.is_clear(point + knee, next + knee) || !map.is_clear(point + head, next + head) {
+                    continue;
+                }
+
+                let Some(floor) = map.floor_below(next + Vec3::Y * STEP_HEIGHT, STEP_HEIGHT + DROP_HEIGHT) else { continue };
+                let neighbour = match grid.find_point(cell, floor) {
+                    Some(neighbour) => neighbour,
+                    None => {
+                        let neighbour = grid.add_point(cell, floor);
+                        open.push(neighbour);
+                        neighbour
+                    }
+                };
+                // drops only go one way
+                if !grid.links[index].contains(&neighbour) {
+                    grid.links[index].push(neighbour);
+                }
+            }
+        }
+
+        grid
+    }
+
+    fn add_point(&mut self, cell: (i32, i32), floor: f32) -> usize {
+        let center = cell_center(cell);
+        self.points.push(Vec3::new(center.x, floor, center.y));
+        self.links.push(Vec::new());
+        self.cells.entry(cell).or_default().push(self.points.len() - 1);
+        self.points.len() - 1
+    }
+
+    fn find_point(&self, cell: (i32, i32), floor: f32) -> Option<usize> {
+        self.cells.get(&cell)?.iter().copied().find(|index| (self.points[*index].y - floor).abs() < 0.2)
+    }
+
+    // The closest grid point to where a player is standing
+    pub fn nearest(&self, feet: Vec3) -> Option<usize> {
+        let (cell_x, cell_z) = cell_of(feet);
+        let mut best: Option<(usize, f32)> = None;
+        for step_x in -2..=2 {
+            for step_z in -2..=2 {
+                let Some(indices) = self.cells.get(&(cell_x + step_x, cell_z + step_z)) else { continue };
+                for index in indices {
+                    let point = self.points[*index];
+                    if (point.y - feet.y).abs() > 0.6 {
+                        continue;
+                    }
+                    let distance = point.xz().distance(feet.xz());
+                    if best.map_or(true, |(_, best_distance)| distance < best_distance) {
+                        best = Some((*index, distance));
+                    }
+                }
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+
+    pub fn eye(&self, index: usize) -> Vec3 {
+        self.points[index] + Vec3::Y * EYE_HEIGHT
+    }
+
+    // Walks outwards from a point until somewhere one of the targets can be seen from,
+    // returns the path there and how long it is
+    pub fn path_to_view(
+        &self,
+        map: &MapShape,
+        from: usize,
+        targets: &[Vec3],
+        target_radius: f32,
+        skip: &HashSet<usize>
+    ) -> Option<(Vec<Vec3>, f32)> {
+        let mut cost = vec![u32::MAX; self.points.len()];
+        let mut previous = vec![usize::MAX; self.points.len()];
+        let mut open = BinaryHeap::new();
+        cost[from] = 0;
+        open.push(Reverse((0, from)));
+
+        while let Some(Reverse((point_cost, index))) = open.pop() {
+            if point_cost > cost[index] {
+                continue;
+            }
+
+            let eye = self.eye(index);
+            if !skip.contains(&index) && targets.iter().any(|target| map.can_see(eye, *target, target_radius)) {
+                let mut path = vec![self.points[index]];
+                let mut step = index;
+                while previous[step] != usize::MAX {
+                    step = previous[step];
+                    path.push(self.points[step]);
+                }
+                path.reverse();
+                return Some((path, point_cost as f32 / 1000.0));
+            }
+
+            for neighbour in &self.links[index] {
+                // costs are kept in millimetres so the heap can order them
+                let step_cost = point_cost + (self.points[index].distance(self.points[*neighbour]) * 1000.0) as u32;
+                if step_cost < cost[*neighbour] {
+                    cost[*neighbour] = step_cost;
+                    previous[*neighbour] = index;
+                    open.push(Reverse((step_cost, *neighbour)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn cell_of(point: Vec3) -> (i32, i32) {
+    ((point.x / CELL).floor() as i32, (point.z / CELL).floor() as i32)
+}
+
+fn cell_center(cell: (i32, i32)) -> Vec2 {
+    Vec2::new((cell.0 as f32 + 0.5) * CELL, (cell.1 as f32 + 0.5) * CELL)
+}
@@ -0,0 +1,600 @@
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*
+};
+use bevy_rapier3d::prelude::*;
+use crate::{
+    GameState,
+    LevelState,
+    structs::{
+        AnalogInput,
+        CameraController,
+        GunController,
+        HitKind,
+        InputAction,
+        PlayerController,
+        Replay,
+        ReplayFrame,
+        ReplayPlayback,
+        ReplayPose,
+        ReplayRecorder,
+        ReplayShot,
+        ReplayStart,
+        Settings,
+        ShotFiredEvent
+    }
+};
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH
+    }
+};
+
+// The fixed timestep the game is simulated at, replays record one frame per tick
+pub const TICK: f32 = 1.0 / 60.0;
+
+const MAGIC: &[u8; 4] = b"FPSR";
+// Only replays of this version are read, changing the format or InputAction::ALL needs a new one
+const VERSION: u8 = 1;
+
+// The pressed and changed actions are stored as one bit each
+const _: () = assert!(InputAction::ALL.len() <= u16::BITS as usize, "the actions don't fit in a replay frame");
+
+// Pausing goes through the menus, which replays don't drive
+const PAUSE_BIT: u16 = 1 << action_index(InputAction::Pause);
+
+const fn action_index(action: InputAction) -> usize {
+    let mut index = 0;
+    while index < InputAction::ALL.len() {
+        if InputAction::ALL[index] as u8 == action as u8 {
+            return index;
+        }
+        index += 1;
+    }
+    panic!("the action is missing from InputAction::ALL");
+}
+
+// The target id of shots that didn't hit a target
+const NO_TARGET: u32 = u32::MAX;
+
+const HAS_MOUSE: u8 = 1;
+const HAS_MOVEMENT: u8 = 2;
+const HAS_LOOK: u8 = 4;
+
+pub fn level_number(level: &LevelState) -> Option<u8> {
+    match level {
+        LevelState::Level1 => Some(1),
+        LevelState::Level2 => Some(2),
+        LevelState::Level3 => Some(3),
+        LevelState::Level4 => Some(4),
+        LevelState::Level5 => Some(5),
+        LevelState::NoLevel | LevelState::Failed => None
+    }
+}
+
+pub fn level_state(number: u8) -> Option<LevelState> {
+    match number {
+        1 => Some(LevelState::Level1),
+        2 => Some(LevelState::Level2),
+        3 => Some(LevelState::Level3),
+        4 => Some(LevelState::Level4),
+        5 => Some(LevelState::Level5),
+        _ => None
+    }
+}
+
+// Records the input of every fixed tick, a new replay starts with the first tick of a level
+#[allow(clippy::too_many_arguments)]
+pub fn record_frame(
+    actions: Res<ButtonInput<InputAction>>,
+    analog_input: Res<AnalogInput>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    mut recorder: ResMut<ReplayRecorder>,
+    current_level: Res<State<LevelState>>,
+    game_state: Res<State<GameState>>,
+    settings: Res<Settings>,
+    player_query: Query<(&Transform, &PlayerController, &Velocity), Without<CameraController>>,
+    camera_query: Query<&CameraController>,
+    gun_query: Query<&GunController>
+) {
+    // sums up the same mouse motion mouse_callback reads this tick
+    let mouse = mouse_motion_events.read().map(|event| event.delta).sum();
+
+    if recorder.current.is_none() {
+        let Some(level) = level_number(current_level.get()) else { return };
+        let (Ok((transform, player, body_velocity)), Ok(camera), Ok(gun)) =
+            (player_query.get_single(), camera_query.get_single(), gun_query.get_single()) else { return };
+
+        recorder.current = Some(Replay {
+            level,
+            cleared: false,
+            ticks: 0,
+            settings: settings.clone(),
+            start: ReplayStart {
+                translation: transform.translation,
+                body_velocity: body_velocity.linvel,
+                velocity: player.velocity,
+                yaw: camera.yaw,
+                pitch: camera.pitch,
+                stamina: player.stamina,
+                crouch_amount: player.crouch_amount,
+                jump_queue: player.jump_queue,
+                aim_amount: gun.aim_amount,
+                spread_seed: gun.spread_seed
+            },
+            frames: Vec::new(),
+            poses: Vec::new()
+        });
+    }
+
+    let Some(replay) = &mut recorder.current else { return };
+    let mut frame = ReplayFrame {
+        mouse,
+        movement: analog_input.movement,
+        look: analog_input.look,
+        ..Default::default()
+    };
+    for (index, action) in InputAction::ALL.iter().enumerate() {
+        if actions.pressed(*action) {
+            frame.pressed |= 1 << index;
+        }
+        if actions.just_pressed(*action) || actions.just_released(*action) {
+            frame.changed |= 1 << index;
+        }
+    }
+    frame.pressed &= !PAUSE_BIT;
+    frame.changed &= !PAUSE_BIT;
+
+    replay.frames.push(frame);
+    if *game_state.get() == GameState::Playing {
+        replay.ticks += 1;
+    }
+}
+
+// Records where the player ended up after a tick and where its shot went, once the gun has fired
+pub fn record_pose(
+    mut shot_events: EventReader<ShotFiredEvent>,
+    mut recorder: ResMut<ReplayRecorder>,
+    player_query: Query<&Transform, With<PlayerController>>,
+    camera_query: Query<&CameraController>
+) {
+    let shot = shot_events.read().last().map(|shot_event| ReplayShot {
+        end: shot_event.end,
+        hit: shot_event.hit.kind(),
+        target_id: shot_event.hit.target_id()
+    });
+    let Some(replay) = &mut recorder.current else { return };
+    let (Ok(transform), Ok(camera)) = (player_query.get_single(), camera_query.get_single()) else { return };
+
+    replay.poses.push(ReplayPose {
+        translation: transform.translation,
+        yaw: camera.yaw,
+        pitch: camera.pitch,
+        shot
+    });
+}
+
+// Finishes the replay once the level is cleared or failed, saving it if there's a directory
+pub fn finish_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    current_level: Res<State<LevelState>>
+) {
+    let playing_back = playback.is_some();
+    let Some(mut replay) = recorder.current.take() else { return };
+    if level_number(current_level.get()) == Some(replay.level) {
+        recorder.current = Some(replay);
+        return;
+    }
+    replay.cleared = *current_level.get() != LevelState::Failed;
+
+    let result = if replay.cleared {
+        format!("cleared in {:.2}s", replay.ticks as f32 * TICK)
+    } else {
+        "failed".to_string()
+    };
+
+    // a replay being played back is recorded again to check it ends the same way
+    if let Some(mut playback) = playback {
+        let matches = replay.cleared == playback.replay.cleared && replay.ticks == playback.replay.ticks;
+        if matches {
+            info!("Replay verified, level {} {}", replay.level, result);
+        } else {
+            warn!("Replay did not play out the same, level {} {} this time", replay.level, result);
+        }
+        playback.verified = Some(matches);
+    } else if let Some(directory) = &recorder.directory {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let path = directory.join(format!("level{}-{}.replay", replay.level, seconds));
+        match save_replay(&path, &replay) {
+            Ok(()) => info!("Saved replay of level {} ({}) to {}", replay.level, result, path.display()),
+            Err(error) => warn!("{}", error)
+        }
+    }
+
+    // playback doesn't count towards personal bests
+    let is_best = !playing_back && replay.cleared
+        && recorder.best.get(&replay.level).map_or(true, |best| replay.ticks < best.ticks);
+    if is_best {
+        if let Some(directory) = &recorder.directory {
+            if let Err(error) = save_replay(&best_replay_path(directory, replay.level), &replay) {
+                warn!("{}", error);
+            }
+        }
+        recorder.best.insert(replay.level, replay.clone());
+    }
+
+    recorder.last = Some(replay);
+}
+
+// Reads the personal bests saved by earlier sessions
+pub fn load_best_replays(
+    mut recorder: ResMut<ReplayRecorder>
+) {
+    let Some(directory) = recorder.directory.clone() else { return };
+    for level in 1..=5 {
+        let path = best_replay_path(&directory, level);
+        if !path.exists() {
+            continue;
+        }
+        match load_replay(&path) {
+            Ok(replay) => {
+                recorder.best.insert(level, replay);
+            }
+            Err(error) => warn!("{}", error)
+        }
+    }
+}
+
+pub fn best_replay_path(directory: &Path, level: u8) -> PathBuf {
+    directory.join(format!("best-level{}.replay", level))
+}
+
+// Quitting to the main menu throws away the level being recorded
+pub fn discard_recording(
+    mut recorder: ResMut<ReplayRecorder>
+) {
+    recorder.current = None;
+}
+
+// Starts the replayed level from the main menu, quitting to the menu starts it over.
+// Not done on startup as nothing moves on the very first update
+pub fn start_playback(
+    mut playback: ResMut<ReplayPlayback>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_level: ResMut<NextState<LevelState>>
+) {
+    playback.index = 0;
+    playback.verified = None;
+    if let Some(level) = level_state(playback.replay.level) {
+        next_level.set(level);
+        next_state.set(GameState::Start);
+    }
+}
+
+// Feeds the recorded input of the next tick to the game systems in place of the live input
+pub fn play_frame(
+    mut playback: ResMut<ReplayPlayback>,
+    mut actions: ResMut<ButtonInput<InputAction>>,
+    mut analog_input: ResMut<AnalogInput>,
+    mut mouse_motion_events: ResMut<Events<MouseMotion>>,
+    mut player_query: Query<(&mut Transform, &mut PlayerController, &mut Velocity), Without<CameraController>>,
+    mut camera_query: Query<(&mut CameraController, &mut Transform), Without<PlayerController>>,
+    mut gun_query: Query<&mut GunController>
+) {
+    // the level only starts once the player exists
+    let Ok((mut transform, mut player, mut body_velocity)) = player_query.get_single_mut() else { return };
+
+    if playback.index == 0 {
+        let start = playback.replay.start;
+        transform.translation = start.translation;
+        transform.rotation = Quat::from_axis_angle(Vec3::Y, start.yaw);
+        body_velocity.linvel = start.body_velocity;
+        player.velocity = start.velocity;
+        player.stamina = start.stamina;
+        player.crouch_amount = start.crouch_amount;
+        player.jump_queue = start.jump_queue;
+        for (mut camera, mut camera_transform) in camera_query.iter_mut() {
+            camera.yaw = start.yaw;
+            camera.pitch = start.pitch;
+            camera_transform.rotation = Quat::from_axis_angle(Vec3::X, start.pitch);
+        }
+        for mut gun in gun_query.iter_mut() {
+            gun.aim_amount = start.aim_amount;
+            gun.spread_seed = start.spread_seed;
+        }
+    }
+
+    let frame = playback.replay.frames.get(playback.index).copied().unwrap_or_default();
+    playback.index += 1;
+
+    // keeps the live pause key so the replay can still be paused
+    let pause = InputAction::Pause;
+    let mut pressed = frame.pressed;
+    let mut changed = frame.changed;
+    if actions.pressed(pause) {
+        pressed |= PAUSE_BIT;
+    }
+    if actions.just_pressed(pause) || actions.just_released(pause) {
+        changed |= PAUSE_BIT;
+    }
+
+    // rebuilds the actions from the last tick's state so just pressed and released match
+    *actions = ButtonInput::default();
+    for (index, action) in InputAction::ALL.iter().enumerate() {
+        let bit = 1 << index;
+        if (pressed & bit != 0) != (changed & bit != 0) {
+            actions.press(*action);
+        }
+    }
+    actions.clear();
+    for (index, action) in InputAction::ALL.iter().enumerate() {
+        let bit = 1 << index;
+        if changed & bit != 0 {
+            if pressed & bit != 0 {
+                actions.press(*action);
+            } else {
+                actions.release(*action);
+            }
+        }
+    }
+
+    analog_input.movement = frame.movement;
+    analog_input.look = frame.look;
+
+    mouse_motion_events.clear();
+    if frame.mouse != Vec2::ZERO {
+        mouse_motion_events.send(MouseMotion { delta: frame.mouse });
+    }
+}
+
+// Uses the recorded gameplay settings but keeps the local display settings
+pub fn playback_settings(local: &Settings, recorded: &Settings) -> Settings {
+    Settings {
+        window_mode: local.window_mode,
+        resolution: local.resolution,
+        vsync: local.vsync,
+        msaa_samples: local.msaa_samples,
+        ghost: local.ghost,
+        ..recorded.clone()
+    }
+}
+
+pub fn save_replay(path: &Path, replay: &Replay) -> Result<(), String> {
+    if let Some(directory) = path.parent() {
+        let _ = fs::create_dir_all(directory);
+    }
+    fs::write(path, encode_replay(replay)?).map_err(|error| format!("Could not save {}: {}", path.display(), error))
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, String> {
+    let bytes = fs::read(path).map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+    decode_replay(&bytes).map_err(|error| format!("Could not read {}: {}", path.display(), error))
+}
+
+// Writes a replay as little endian bytes, frames without mouse or stick input are 5 bytes,
+// the poses for the ghost follow after the frames
+pub fn encode_replay(replay: &Replay) -> Result<Vec<u8>, String> {
+    let settings = ron::to_string(&replay.settings).map_err(|error| format!("Could not serialize settings: {}", error))?;
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.push(replay.level);
+    bytes.push(replay.cleared as u8);
+    bytes.extend_from_slice(&replay.ticks.to_le_bytes());
+    bytes.extend_from_slice(&TICK.to_le_bytes());
+    bytes.extend_from_slice(&(settings.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(settings.as_bytes());
+
+    let start = &replay.start;
+    for value in [start.translation, start.body_velocity, start.velocity] {
+        write_vec3(&mut bytes, value);
+    }
+    for value in [start.yaw, start.pitch, start.stamina, start.crouch_amount, start.jump_queue, start.aim_amount] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(&start.spread_seed.to_le_bytes());
+
+    bytes.extend_from_slice(&(replay.frames.len() as u32).to_le_bytes());
+    for frame in &replay.frames {
+        bytes.extend_from_slice(&frame.pressed.to_le_bytes());
+        bytes.extend_from_slice(&frame.changed.to_le_bytes());
+
+        let mut flags = 0;
+        if frame.mouse != Vec2::ZERO {
+            flags |= HAS_MOUSE;
+        }
+        if frame.movement != Vec2::ZERO {
+            flags |= HAS_MOVEMENT;
+        }
+        if frame.look != Vec2::ZERO {
+            flags |= HAS_LOOK;
+        }
+        bytes.push(flags);
+
+        for (flag, value) in [(HAS_MOUSE, frame.mouse), (HAS_MOVEMENT, frame.movement), (HAS_LOOK, frame.look)] {
+            if flags & flag != 0 {
+                bytes.extend_from_slice(&value.x.to_le_bytes());
+                bytes.extend_from_slice(&value.y.to_le_bytes());
+            }
+        }
+    }
+
+    bytes.extend_from_slice(&(replay.poses.len() as u32).to_le_bytes());
+    for pose in &replay.poses {
+        write_vec3(&mut bytes, pose.translation);
+        bytes.extend_from_slice(&pose.yaw.to_le_bytes());
+        bytes.extend_from_slice(&pose.pitch.to_le_bytes());
+        bytes.push(pose.shot.is_some() as u8);
+        if let Some(shot) = pose.shot {
+            write_vec3(&mut bytes, shot.end);
+            bytes.push(hit_code(shot.hit));
+            bytes.extend_from_slice(&shot.target_id.map_or(NO_TARGET, |id| id as u32).to_le_bytes());
+        }
+    }
+
+    Ok(bytes)
+}
+
+pub fn decode_replay(bytes: &[u8]) -> Result<Replay, String> {
+    let mut reader = ByteReader { bytes, position: 0 };
+
+    if reader.take(4)? != MAGIC {
+        return Err("not a replay file".to_string());
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+        return Err(format!("unsupported replay version {}", version));
+    }
+    let level = reader.u8()?;
+    if level_state(level).is_none() {
+        return Err(format!("unknown level {}", level));
+    }
+    let cleared = reader.u8()? != 0;
+    let ticks = reader.u32()?;
+    let tick = reader.f32()?;
+    if tick != TICK {
+        return Err(format!("recorded at a tick of {}s instead of {}s", tick, TICK));
+    }
+
+    let settings_length = reader.u32()? as usize;
+    let settings = std::str::from_utf8(reader.take(settings_length)?).map_err(|error| error.to_string())?;
+    let settings = ron::from_str::<Settings>(settings).map_err(|error| error.to_string())?;
+
+    let start = ReplayStart {
+        translation: reader.vec3()?,
+        body_velocity: reader.vec3()?,
+        velocity: reader.vec3()?,
+        yaw: reader.f32()?,
+        pitch: reader.f32()?,
+        stamina: reader.f32()?,
+        crouch_amount: reader.f32()?,
+        jump_queue: reader.f32()?,
+        aim_amount: reader.f32()?,
+        spread_seed: reader.u32()?
+    };
+
+    let frame_count = reader.u32()? as usize;
+    let mut frames = Vec::with_capacity(frame_count.min(bytes.len()));
+    for _ in 0..frame_count {
+        let pressed = reader.u16()?;
+        let changed = reader.u16()?;
+        let flags = reader.u8()?;
+        let mut vec2_if = |flag: u8| -> Result<Vec2, String> {
+            if flags & flag != 0 {
+                Ok(Vec2::new(reader.f32()?, reader.f32()?))
+            } else {
+                Ok(Vec2::ZERO)
+            }
+        };
+        frames.push(ReplayFrame {
+            pressed,
+            changed,
+            mouse: vec2_if(HAS_MOUSE)?,
+            movement: vec2_if(HAS_MOVEMENT)?,
+            look: vec2_if(HAS_LOOK)?
+        });
+    }
+
+    let pose_count = reader.u32()? as usize;
+    let mut poses = Vec::with_capacity(pose_count.min(bytes.len()));
+    for _ in 0..pose_count {
+        let translation = reader.vec3()?;
+        let yaw = reader.f32()?;
+        let pitch = reader.f32()?;
+        let shot = if reader.u8()? != 0 {
+            let end = reader.vec3()?;
+            let hit = hit_kind(reader.u8()?)?;
+            let target_id = reader.u32()?;
+            Some(ReplayShot {
+                end,
+                hit,
+                target_id: (target_id != NO_TARGET).then_some(target_id as usize)
+            })
+        } else {
+            None
+        };
+        poses.push(ReplayPose {
+            translation,
+            yaw,
+            pitch,
+            shot
+        });
+    }
+
+    Ok(Replay {
+        level,
+        cleared,
+        ticks,
+        settings,
+        start,
+        frames,
+        poses
+    })
+}
+
+fn hit_code(hit: HitKind) -> u8 {
+    match hit {
+        HitKind::Nothing => 0,
+        HitKind::Map => 1,
+        HitKind::StartButton => 2,
+        HitKind::Target => 3
+    }
+}
+
+fn hit_kind(code: u8) -> Result<HitKind, String> {
+    match code {
+        0 => Ok(HitKind::Nothing),
+        1 => Ok(HitKind::Map),
+        2 => Ok(HitKind::StartButton),
+        3 => Ok(HitKind::Target),
+        _ => Err(format!("unknown shot hit {}", code))
+    }
+}
+
+fn write_vec3(bytes: &mut Vec<u8>, value: Vec3) {
+    for component in value.to_array() {
+        bytes.extend_from_slice(&component.to_le_bytes());
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl ByteReader<'_> {
+    fn take(&mut self, length: usize) -> Result<&[u8], String> {
+        let end = self.position + length;
+        let slice = self.bytes.get(self.position..end).ok_or("the replay file ends early")?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, String> {
+        Ok(Vec3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+}
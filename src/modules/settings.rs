@@ -0,0 +1,442 @@
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    window::{
+        PresentMode,
+        PrimaryWindow,
+        WindowMode
+    }
+};
+use crate::{
+    crosshair::{
+        color_name,
+        cycle_color
+    },
+    structs::{
+        CameraController,
+        ConfigDirectory,
+        MovementModel,
+        PlayerController,
+        SensitivityImport,
+        SettingField,
+        Settings,
+        ViewModelCamera
+    }
+};
+use std::fs;
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+const RESOLUTIONS: [(u32, u32); 6] = [
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1080),
+    (2560, 1440),
+    (3840, 2160)
+];
+
+const WINDOW_MODES: [WindowMode; 3] = [
+    WindowMode::Windowed,
+    WindowMode::BorderlessFullscreen,
+    WindowMode::Fullscreen
+];
+
+const MSAA_SAMPLES: [u32; 4] = [1, 2, 4, 8];
+
+// Loads the settings from the config file, writes the defaults if there is none
+pub fn load_settings(config: &ConfigDirectory) -> Settings {
+    let Some(directory) = &config.0 else { return Settings::default() };
+    let path = directory.join(SETTINGS_FILE);
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => match ron::from_str::<Settings>(&contents) {
+            Ok(settings) => settings,
+            Err(error) => {
+                warn!("Could not parse {}: {}", path.display(), error);
+                Settings::default()
+            }
+        },
+        Err(_) => {
+            let settings = Settings::default();
+            save_settings(config, &settings);
+            settings
+        }
+    }
+}
+
+// Writes the settings to the config file
+pub fn save_settings(config: &ConfigDirectory, settings: &Settings) {
+    let Some(directory) = &config.0 else { return };
+    let path = directory.join(SETTINGS_FILE);
+    let _ = fs::create_dir_all(directory);
+
+    match ron::ser::to_string_pretty(settings, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => {
+            if let Err(error) = fs::write(&path, contents) {
+                warn!("Could not save {}: {}", path.display(), error);
+            }
+        }
+        Err(error) => warn!("Could not serialize settings: {}", error)
+    }
+}
+
+// Saves the settings when leaving one of the settings menus
+pub fn save_settings_on_exit(
+    settings: Res<Settings>,
+    config: Res<ConfigDirectory>
+) {
+    save_settings(&config, &settings);
+}
+
+// Saves the settings when the game quits, even from inside a menu
+pub fn save_settings_on_quit(
+    mut app_exit_events: EventReader<AppExit>,
+    settings: Res<Settings>,
+    config: Res<ConfigDirectory>
+) {
+    if app_exit_events.read().count() > 0 {
+        save_settings(&config, &settings);
+    }
+}
+
+// Applies changed settings to the window and the view model camera, the view model itself
+// is placed from the settings by animate_view_model
+pub fn apply_settings(
+    settings: Res<Settings>,
+    mut msaa: ResMut<Msaa>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut viewmodel_camera_query: Query<&mut Projection, With<ViewModelCamera>>,
+    mut applied: Local<Option<Settings>>
+) {
+    // the display is only touched for the fields that changed, the window can be resized or
+    // scaled on its own in between
+    let previous = applied.replace(settings.clone());
+    let previous = previous.as_ref();
+
+    if let Ok(mut window) = window_query.get_single_mut() {
+        if previous.map(|previous| previous.window_mode) != Some(settings.window_mode) {
+            window.mode = settings.window_mode;
+        }
+        if previous.map(|previous| previous.resolution) != Some(settings.resolution) {
+            let (width, height) = settings.resolution;
+            window.resolution.set(width as f32, height as f32);
+        }
+        if previous.map(|previous| previous.vsync) != Some(settings.vsync) {
+            window.present_mode = present_mode(&settings);
+        }
+    }
+
+    if previous.map(|previous| previous.msaa_samples) != Some(settings.msaa_samples) {
+        *msaa = msaa_from_samples(settings.msaa_samples);
+    }
+
+    for mut projection in viewmodel_camera_query.iter_mut() {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = vertical_fov(settings.viewmodel_fov);
+        }
+    }
+}
+
+// Sets the camera fov from the settings and the sights' zoom, only writing it when it changes
+pub fn update_camera_fov(
+    settings: Res<Settings>,
+    mut camera_query: Query<(&CameraController, &mut Projection), Without<ViewModelCamera>>
+) {
+    for (camera, mut projection) in camera_query.iter_mut() {
+        let fov = camera_fov(&settings, camera.zoom);
+        if let Projection::Perspective(perspective) = projection.as_ref() {
+            if perspective.fov != fov {
+                *projection = Projection::Perspective(PerspectiveProjection { fov, ..perspective.clone() });
+            }
+        }
+    }
+}
+
+// The vertical fov in radians the camera uses for the fov setting and a zoom
+pub fn camera_fov(settings: &Settings, zoom: f32) -> f32 {
+    zoomed_fov(vertical_fov(settings.fov), zoom)
+}
+
+// Converts a horizontal fov in degrees at 4:3 to the vertical fov in radians bevy uses,
+// keeping the vertical fov fixed is what makes wider aspect ratios hor+
+pub fn vertical_fov(horizontal_fov: f32) -> f32 {
+    2.0 * ((horizontal_fov.to_radians() / 2.0).tan() * 3.0 / 4.0).atan()
+}
+
+// Narrows a vertical fov by a zoom ratio
+pub fn zoomed_fov(fov: f32, zoom: f32) -> f32 {
+    2.0 * ((fov / 2.0).tan() / zoom.max(1.0)).atan()
+}
+
+// Returns where the view model sits under the camera
+pub fn viewmodel_position(player_controller: &PlayerController, settings: &Settings) -> Vec3 {
+    let mut position = player_controller.view_model + settings.viewmodel_offset;
+    if settings.left_handed {
+        position.x = -position.x;
+    }
+    position
+}
+
+pub fn present_mode(settings: &Settings) -> PresentMode {
+    if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    }
+}
+
+pub fn msaa_from_samples(samples: u32) -> Msaa {
+    match samples {
+        0 | 1 => Msaa::Off,
+        2 => Msaa::Sample2,
+        4 => Msaa::Sample4,
+        _ => Msaa::Sample8
+    }
+}
+
+// Steps to the next or previous entry of a list, wrapping around
+fn cycle<T: PartialEq + Copy>(options: &[T], current: T, step: f32) -> T {
+    let index = options.iter().position(|option| *option == current).unwrap_or(0);
+    let next = if step > 0.0 {
+        (index + 1) % options.len()
+    } else {
+        (index + options.len() - 1) % options.len()
+    };
+    options[next]
+}
+
+// Changes a setting by a step, keeping it inside a sane range
+pub fn adjust_setting(
+    settings: &mut Settings,
+    import: &mut SensitivityImport,
+    field: SettingField,
+    step: f32
+) {
+    match field {
+        SettingField::Movement => {
+            settings.movement_model = cycle(&[MovementModel::Arcade, MovementModel::Source], settings.movement_model, step);
+        }
+        SettingField::WindowMode => {
+            settings.window_mode = cycle(&WINDOW_MODES, settings.window_mode, step);
+        }
+        SettingField::Resolution => {
+            settings.resolution = cycle(&RESOLUTIONS, settings.resolution, step);
+        }
+        SettingField::Vsync => {
+            settings.vsync = !settings.vsync;
+        }
+        SettingField::Msaa => {
+            settings.msaa_samples = cycle(&MSAA_SAMPLES, settings.msaa_samples, step);
+        }
+        SettingField::Fov => {
+            settings.fov = (settings.fov + step).clamp(60.0, 120.0);
+        }
+        SettingField::ViewmodelFov => {
+            settings.viewmodel_fov = (settings.viewmodel_fov + step).clamp(54.0, 120.0);
+        }
+        SettingField::ViewmodelX => {
+            settings.viewmodel_offset.x = (settings.viewmodel_offset.x + step).clamp(-0.1, 0.1);
+        }
+        SettingField::ViewmodelY => {
+            settings.viewmodel_offset.y = (settings.viewmodel_offset.y + step).clamp(-0.1, 0.1);
+        }
+        SettingField::ViewmodelZ => {
+            settings.viewmodel_offset.z = (settings.viewmodel_offset.z + step).clamp(-0.1, 0.1);
+        }
+        SettingField::ViewmodelHand => {
+            settings.left_handed = !settings.left_handed;
+        }
+        SettingField::MasterVolume
+        | SettingField::AmbienceVolume
+        | SettingField::WeaponVolume
+        | SettingField::UiVolume => {
+            let volume = slider_value(settings, field).unwrap_or(0.0);
+            set_slider_value(settings, field, volume + step);
+        }
+        SettingField::MuteUnfocused => {
+            settings.mute_unfocused = !settings.mute_unfocused;
+        }
+        SettingField::Sensitivity => {
+            settings.sensitivity = (settings.sensitivity + step).clamp(0.05, 10.0);
+        }
+        SettingField::SensitivityX => {
+            settings.sensitivity_x = (settings.sensitivity_x + step).clamp(0.1, 3.0);
+        }
+        SettingField::SensitivityY => {
+            settings.sensitivity_y = (settings.sensitivity_y + step).clamp(0.1, 3.0);
+        }
+        SettingField::MouseDpi => {
+            settings.mouse_dpi = (settings.mouse_dpi + step).clamp(100.0, 6400.0);
+        }
+        SettingField::ImportSensitivity => {
+            let step = step * import.game.sensitivity_step();
+            import.sensitivity = (import.sensitivity + step).clamp(0.0, 100.0);
+        }
+        SettingField::ImportDpi => {
+            import.dpi = (import.dpi + step).clamp(100.0, 6400.0);
+        }
+        SettingField::CrosshairGap => {
+            settings.crosshair.gap = (settings.crosshair.gap + step).clamp(-10.0, 30.0);
+        }
+        SettingField::CrosshairLength => {
+            settings.crosshair.length = (settings.crosshair.length + step).clamp(0.0, 40.0);
+        }
+        SettingField::CrosshairThickness => {
+            settings.crosshair.thickness = (settings.crosshair.thickness + step).clamp(1.0, 10.0);
+        }
+        SettingField::CrosshairOutline => {
+            settings.crosshair.outline = !settings.crosshair.outline;
+        }
+        SettingField::CrosshairOutlineThickness => {
+            settings.crosshair.outline_thickness = (settings.crosshair.outline_thickness + step).clamp(1.0, 3.0);
+        }
+        SettingField::CrosshairColor => {
+            settings.crosshair.color = cycle_color(settings.crosshair.color, step);
+        }
+        SettingField::CrosshairAlpha => {
+            let alpha = (settings.crosshair.color.a() + step).clamp(0.1, 1.0);
+            settings.crosshair.color.set_a(alpha);
+        }
+        SettingField::CrosshairDot => {
+            settings.crosshair.dot = !settings.crosshair.dot;
+        }
+        SettingField::CrosshairDynamic => {
+            settings.crosshair.dynamic = !settings.crosshair.dynamic;
+        }
+        SettingField::CrosshairHitMarker => {
+            settings.crosshair.hit_marker = !settings.crosshair.hit_marker;
+        }
+        SettingField::DamageNumbers => {
+            settings.damage_numbers = !settings.damage_numbers;
+        }
+        SettingField::BulletDecals => {
+            settings.bullet_decals = !settings.bullet_decals;
+        }
+        SettingField::Tracers => {
+            settings.tracers = !settings.tracers;
+        }
+        SettingField::MuzzleFlash => {
+            settings.muzzle_flash = !settings.muzzle_flash;
+        }
+        SettingField::ZoomSensitivity => {
+            settings.zoom_sensitivity = (settings.zoom_sensitivity + step).clamp(0.1, 3.0);
+        }
+        SettingField::Ghost => {
+            settings.ghost = !settings.ghost;
+        }
+    }
+}
+
+// Returns the 0 to 1 value of settings that are shown as sliders
+pub fn slider_value(settings: &Settings, field: SettingField) -> Option<f32> {
+    match field {
+        SettingField::MasterVolume => Some(settings.master_volume),
+        SettingField::AmbienceVolume => Some(settings.ambience_volume),
+        SettingField::WeaponVolume => Some(settings.weapon_volume),
+        SettingField::UiVolume => Some(settings.ui_volume),
+        _ => None
+    }
+}
+
+pub fn set_slider_value(settings: &mut Settings, field: SettingField, value: f32) {
+    let value = value.clamp(0.0, 1.0);
+    match field {
+        SettingField::MasterVolume => settings.master_volume = value,
+        SettingField::AmbienceVolume => settings.ambience_volume = value,
+        SettingField::WeaponVolume => settings.weapon_volume = value,
+        SettingField::UiVolume => settings.ui_volume = value,
+        _ => {}
+    }
+}
+
+// Returns the text shown next to a setting
+pub fn setting_label(
+    settings: &Settings,
+    import: &SensitivityImport,
+    field: SettingField
+) -> String {
+    match field {
+        SettingField::Movement => match settings.movement_model {
+            MovementModel::Arcade => "ARCADE".to_string(),
+            MovementModel::Source => "SOURCE".to_string()
+        },
+        SettingField::WindowMode => match settings.window_mode {
+            WindowMode::Windowed => "WINDOWED".to_string(),
+            WindowMode::BorderlessFullscreen => "BORDERLESS".to_string(),
+            WindowMode::SizedFullscreen | WindowMode::Fullscreen => "FULLSCREEN".to_string()
+        },
+        SettingField::Resolution => format!("{}x{}", settings.resolution.0, settings.resolution.1),
+        SettingField::Vsync => if settings.vsync { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::Msaa => if settings.msaa_samples <= 1 {
+            "OFF".to_string()
+        } else {
+            format!("{}x", settings.msaa_samples)
+        },
+        SettingField::Fov => format!("{:.0}", settings.fov),
+        SettingField::ViewmodelFov => format!("{:.0}", settings.viewmodel_fov),
+        SettingField::ViewmodelX => format!("{:.2}", settings.viewmodel_offset.x),
+        SettingField::ViewmodelY => format!("{:.2}", settings.viewmodel_offset.y),
+        SettingField::ViewmodelZ => format!("{:.2}", settings.viewmodel_offset.z),
+        SettingField::ViewmodelHand => if settings.left_handed { "LEFT".to_string() } else { "RIGHT".to_string() },
+        SettingField::MasterVolume
+        | SettingField::AmbienceVolume
+        | SettingField::WeaponVolume
+        | SettingField::UiVolume => format!("{:.0}%", slider_value(settings, field).unwrap_or(0.0) * 100.0),
+        SettingField::MuteUnfocused => if settings.mute_unfocused { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::Sensitivity => format!(
+            "{:.2} ({:.1} cm/360)",
+            settings.sensitivity,
+            cm_per_360(degrees_per_count(settings.sensitivity * settings.sensitivity_x), settings.mouse_dpi)
+        ),
+        SettingField::SensitivityX => format!("{:.2}", settings.sensitivity_x),
+        SettingField::SensitivityY => format!("{:.2}", settings.sensitivity_y),
+        SettingField::MouseDpi => format!("{:.0}", settings.mouse_dpi),
+        SettingField::ImportSensitivity => {
+            let converted = convert_sensitivity(import, settings.mouse_dpi);
+            format!(
+                "{} {:.3} = {:.2} ({:.1} cm/360)",
+                import.game.name(),
+                import.sensitivity,
+                converted,
+                cm_per_360(degrees_per_count(converted), settings.mouse_dpi)
+            )
+        }
+        SettingField::ImportDpi => format!("{:.0}", import.dpi),
+        SettingField::CrosshairGap => format!("{:.0}", settings.crosshair.gap),
+        SettingField::CrosshairLength => format!("{:.0}", settings.crosshair.length),
+        SettingField::CrosshairThickness => format!("{:.0}", settings.crosshair.thickness),
+        SettingField::CrosshairOutline => if settings.crosshair.outline { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::CrosshairOutlineThickness => format!("{:.1}", settings.crosshair.outline_thickness),
+        SettingField::CrosshairColor => color_name(settings.crosshair.color),
+        SettingField::CrosshairAlpha => format!("{:.0}%", settings.crosshair.color.a() * 100.0),
+        SettingField::CrosshairDot => if settings.crosshair.dot { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::CrosshairDynamic => if settings.crosshair.dynamic { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::CrosshairHitMarker => if settings.crosshair.hit_marker { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::DamageNumbers => if settings.damage_numbers { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::BulletDecals => if settings.bullet_decals { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::Tracers => if settings.tracers { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::MuzzleFlash => if settings.muzzle_flash { "ON".to_string() } else { "OFF".to_string() },
+        SettingField::ZoomSensitivity => format!("{:.2}", settings.zoom_sensitivity),
+        SettingField::Ghost => if settings.ghost { "ON".to_string() } else { "OFF".to_string() }
+    }
+}
+
+// Degrees turned per mouse count at a given sensitivity in this game
+pub fn degrees_per_count(sensitivity: f32) -> f32 {
+    (CameraController::default().sensitivity * sensitivity).to_degrees()
+}
+
+// Centimeters of mouse movement needed for a full turn
+pub fn cm_per_360(degrees_per_count: f32, dpi: f32) -> f32 {
+    if degrees_per_count <= 0.0 || dpi <= 0.0 {
+        return 0.0;
+    }
+    360.0 / (degrees_per_count * dpi) * 2.54
+}
+
+// Converts another game's sensitivity to this game's, matching the turn per centimeter
+pub fn convert_sensitivity(import: &SensitivityImport, mouse_dpi: f32) -> f32 {
+    let degrees_per_inch = import.sensitivity * import.game.yaw() * import.dpi;
+    degrees_per_inch / (degrees_per_count(1.0) * mouse_dpi)
+}
@@ -1,6 +1,24 @@
-use bevy::prelude::*;
-use crate::GameState;
-use std::time::Duration;
+use bevy::{
+    prelude::*,
+    window::WindowMode
+};
+use bevy_rapier3d::prelude::Collider;
+use crate::{
+    GameState,
+    LevelState
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap
+    },
+    path::PathBuf,
+    time::Duration
+};
 
 #[derive(Component, Debug, Resource)]
 pub struct PlayerController {
@@ -12,6 +30,19 @@ pub struct PlayerController {
     pub crouch_modifier: f32,
     pub is_grounded: bool,
     pub is_crouched: bool,
+    pub is_sprinting: bool,
+    pub crouch_amount: f32,
+    pub crouch_speed: f32,
+    pub stamina: f32,
+    pub max_stamina: f32,
+    pub stamina_regen: f32,
+    pub sprint_modifier: f32,
+    pub ground_accelerate: f32,
+    pub air_accelerate: f32,
+    pub ground_friction: f32,
+    pub stop_speed: f32,
+    pub max_air_speed: f32,
+    pub jump_queue: f32,
 }
 
 impl Default for PlayerController {
@@ -25,19 +56,192 @@ impl Default for PlayerController {
             velocity: Vec3::ZERO,
             is_grounded: true,
             is_crouched: false,
+            is_sprinting: false,
+            crouch_amount: 0.0,
+            crouch_speed: 8.0,
+            stamina: 3.0,
+            max_stamina: 3.0,
+            stamina_regen: 0.75,
+            sprint_modifier: 1.35,
+            // source values (sv_accelerate, sv_airaccelerate, sv_friction, sv_stopspeed)
+            // scaled from hammer units, 250u/s being equal to 'speed'
+            ground_accelerate: 5.5,
+            air_accelerate: 12.0,
+            ground_friction: 5.2,
+            stop_speed: 1.02,
+            max_air_speed: 0.384,
+            jump_queue: 0.0,
         }
     }
 }
 
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TargetHitEvent {
+    pub target: Entity,
+    pub position: Vec3,
+    pub damage: i32,
+    pub killed: bool
+}
+
+// A sound made somewhere in the world, heard from where it happened. Nothing sends these yet,
+// they are for the gunfire and footsteps of bots
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WorldSoundEvent {
+    pub position: Vec3,
+    pub sound: WorldSound
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldSound {
+    Gunshot,
+    Footstep
+}
+
+// origin is the barrel for the tracer, the bullet itself goes from the camera
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShotFiredEvent {
+    pub origin: Vec3,
+    pub end: Vec3,
+    pub camera_origin: Vec3,
+    pub direction: Vec3,
+    pub hit: ShotHit
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShotHit {
+    Nothing,
+    Map,
+    StartButton(Entity),
+    Target {
+        entity: Entity,
+        id: usize
+    }
+}
+
+impl ShotHit {
+    pub fn kind(&self) -> HitKind {
+        match self {
+            ShotHit::Nothing => HitKind::Nothing,
+            ShotHit::Map => HitKind::Map,
+            ShotHit::StartButton(_) => HitKind::StartButton,
+            ShotHit::Target { .. } => HitKind::Target
+        }
+    }
+
+    pub fn target_id(&self) -> Option<usize> {
+        match self {
+            ShotHit::Target { id, .. } => Some(*id),
+            _ => None
+        }
+    }
+}
+
+// What a shot hit without the entity, for what is saved to files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HitKind {
+    #[default]
+    Nothing,
+    Map,
+    StartButton,
+    Target
+}
+
+impl HitKind {
+    // Same names as in the json files
+    pub fn name(&self) -> &'static str {
+        match self {
+            HitKind::Nothing => "nothing",
+            HitKind::Map => "map",
+            HitKind::StartButton => "start_button",
+            HitKind::Target => "target"
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BulletImpactEvent {
+    pub position: Vec3,
+    pub normal: Vec3
+}
+
+#[derive(Component)]
+pub struct Decal {
+    pub age: f32
+}
+
+#[derive(Component)]
+pub struct Tracer {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub traveled: f32
+}
+
+#[derive(Component)]
+pub struct ViewModelAnimation {
+    pub bob_phase: f32,
+    pub bob_amount: f32,
+    pub sway: Vec2,
+    pub recoil: f32,
+    pub draw_time: f32,
+    pub last_position: Option<Vec3>
+}
+
+impl Default for ViewModelAnimation {
+    fn default() -> Self {
+        Self {
+            bob_phase: 0.0,
+            bob_amount: 0.0,
+            sway: Vec2::ZERO,
+            recoil: 0.0,
+            draw_time: 0.0,
+            last_position: None
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MuzzleFlash {
+    pub time_left: f32
+}
+
+#[derive(Resource, Default)]
+pub struct EffectsController {
+    pub decal_mesh: Option<Handle<Mesh>>,
+    // one material per step of the fade, from transparent to opaque
+    pub decal_materials: Vec<Handle<StandardMaterial>>,
+    pub tracer_mesh: Option<Handle<Mesh>>,
+    pub tracer_material: Option<Handle<StandardMaterial>>,
+    pub flash_mesh: Option<Handle<Mesh>>,
+    pub flash_material: Option<Handle<StandardMaterial>>,
+    pub ghost_mesh: Option<Handle<Mesh>>,
+    pub ghost_material: Option<Handle<StandardMaterial>>
+}
+
+#[derive(Component)]
+pub struct Dying {
+    pub timer: Timer,
+    pub start_scale: Vec3
+}
+
+#[derive(Component)]
+pub struct DamageNumber {
+    pub position: Vec3,
+    pub age: f32
+}
+
 #[derive(Component, Debug, Resource)]
 pub struct TargetController {
     pub health: i32,
+    // index in the level's target positions
+    pub id: usize
 }
 
 impl Default for TargetController {
     fn default() -> Self {
         Self {
-            health: 1
+            health: 1,
+            id: 0
         }
     }
 }
@@ -49,57 +253,86 @@ pub struct GunController {
     pub just_pressed: bool,
     pub is_rotated: bool,
     pub model_handle: Option<Handle<Scene>>,
-    pub play_audio: bool
+    // only drives the reload animation, the magazine never runs out
+    pub reload_timer: Option<Timer>,
+    pub sight_offset: Vec3,
+    pub ads_zoom: f32,
+    pub ads_time: f32,
+    pub scope: Option<Scope>,
+    pub aiming: bool,
+    pub aim_amount: f32,
+    pub spread_seed: u32
 }
 
 impl Default for GunController {
     fn default() -> Self {
         Self {
             shooting: false,
-            bullet_delay: None,
+            // 600 rounds per minute
+            bullet_delay: Some(Timer::from_seconds(0.1, TimerMode::Repeating)),
             just_pressed: false,
             is_rotated: false,
             model_handle: None,
-            play_audio: false
+            reload_timer: None,
+            // where the view model sits when aiming down the sights
+            sight_offset: Vec3::new(0.0, -0.16, 0.30),
+            ads_zoom: 1.25,
+            ads_time: 0.15,
+            scope: None,
+            aiming: false,
+            aim_amount: 0.0,
+            // the same for every default gun, the game seeds the gun it spawns randomly
+            spread_seed: 0x9e37_79b9
         }
     }
 }
 
-#[derive(Component, Resource)]
-pub struct MapController {
-    pub is_rotated: bool,
-    pub scene_handle: Option<Handle<Scene>>
+// A scoped weapon zooms further, shows the scope overlay and is inaccurate when fired unscoped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scope {
+    pub zoom: f32,
+    pub unscoped_spread: f32
 }
 
-impl Default for MapController {
+impl Default for Scope {
     fn default() -> Self {
         Self {
-            is_rotated: false,
-            scene_handle: None
+            zoom: 4.0,
+            // radians
+            unscoped_spread: 0.1
         }
     }
 }
 
-#[derive(Resource)]
+#[derive(Component)]
+pub struct ScopeOverlay;
+
+// The map collision loaded straight from the glTF file, for running without a renderer
+#[derive(Resource, Default)]
+pub struct MapCollider {
+    pub collider: Option<Collider>
+}
+
+#[derive(Component, Resource, Default)]
+pub struct MapController {
+    pub is_rotated: bool,
+    pub scene_handle: Option<Handle<Scene>>
+}
+
+#[derive(Resource, Default)]
 pub struct CubemapController {
     pub is_loaded: bool,
     pub image_handle: Option<Handle<Image>>
 }
 
-impl Default for CubemapController {
-    fn default() -> Self {
-        Self {
-            is_loaded: false,
-            image_handle: None
-        }
-    }
-}
-
 #[derive(Component, Debug)]
 pub struct CameraController {
     pub pitch: f32,
     pub yaw: f32,
     pub sensitivity: f32,
+    pub zoom_sensitivity: f32,
+    // how far aiming down the sights zooms the view in
+    pub zoom: f32
 }
 
 impl Default for CameraController {
@@ -108,6 +341,8 @@ impl Default for CameraController {
             pitch: 0.0,
             yaw: 0.0,
             sensitivity: 0.00045,
+            zoom_sensitivity: 1.0,
+            zoom: 1.0
         }
     }
 }
@@ -118,23 +353,16 @@ pub struct FpsText;
 #[derive(Component)]
 pub struct LevelText;
 
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct TargetText {
+    #[allow(dead_code)]
     pub targets_left: Option<usize>
 }
 
-impl Default for TargetText {
-    fn default() -> Self {
-        Self {
-            targets_left: None
-        }
-    }
-}
-
 #[derive(Component)]
 pub struct TimeText;
 
-#[derive(Component, Resource, Debug, Default)]
+#[derive(Component, Resource, Debug)]
 pub struct TimeController {
     pub time_left: Option<Timer>,
     pub level_1_time: f32,
@@ -174,8 +402,10 @@ impl TimeController {
             "No timer".to_string()
         }
     }
+}
 
-    pub fn default() -> Self {
+impl Default for TimeController {
+    fn default() -> Self {
         Self {
             time_left: None,
             level_1_time: 50.0,
@@ -188,19 +418,43 @@ impl TimeController {
 }
 
 #[derive(Component)]
-pub struct Ambience;
+pub struct Ambience {
+    pub handle: Handle<AudioSource>,
+    pub elapsed: f32,
+    pub gain: f32,
+    pub fading_out: bool
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioBus {
+    Ambience,
+    Weapon,
+    Ui
+}
 
 #[derive(Resource)]
 pub struct AudioController {
     pub ambience_handle: Option<Handle<AudioSource>>,
-    pub gunshot_handle: Option<Handle<AudioSource>>
+    pub gunshot_handle: Option<Handle<AudioSource>>,
+    pub click_handle: Option<Handle<Pitch>>,
+    pub hit_handle: Option<Handle<Pitch>>,
+    pub destroy_handle: Option<Handle<Pitch>>,
+    pub footstep_handle: Option<Handle<Pitch>>,
+    pub window_focused: bool,
+    pub ambience_durations: HashMap<AssetId<AudioSource>, f32>
 }
 
 impl Default for AudioController {
     fn default() -> Self {
         Self {
             ambience_handle: None,
-            gunshot_handle: None
+            gunshot_handle: None,
+            click_handle: None,
+            hit_handle: None,
+            destroy_handle: None,
+            footstep_handle: None,
+            window_focused: true,
+            ambience_durations: HashMap::new()
         }
     }
 }
@@ -211,7 +465,9 @@ pub struct LevelController {
     pub level_2_pos: Vec<Vec3>,
     pub level_3_pos: Vec<Vec3>,
     pub level_4_pos: Vec<Vec3>,
-    pub level_5_pos: Vec<Vec3>
+    pub level_5_pos: Vec<Vec3>,
+    // the ambience each level plays, levels without one play the shared track
+    pub ambience_tracks: [Option<&'static str>; 5]
 }
 
 impl Default for LevelController {
@@ -388,7 +644,22 @@ impl Default for LevelController {
                 Vec3::new(11.2624, -0.525, 3.5596),
                 Vec3::new(9.8675, 0.5495, -1.3613),
                 Vec3::new(10.9497, 1.603, 0.9789)
-            ]
+            ],
+            // every level is on dust2, so they all use the shared track for now
+            ambience_tracks: [None; 5]
+        }
+    }
+}
+
+impl LevelController {
+    pub fn ambience_track(&self, level: &LevelState) -> Option<&'static str> {
+        match level {
+            LevelState::Level1 => self.ambience_tracks[0],
+            LevelState::Level2 => self.ambience_tracks[1],
+            LevelState::Level3 => self.ambience_tracks[2],
+            LevelState::Level4 => self.ambience_tracks[3],
+            LevelState::Level5 => self.ambience_tracks[4],
+            LevelState::NoLevel | LevelState::Failed => None
         }
     }
 }
@@ -398,7 +669,21 @@ pub enum MenuButtonAction {
     Play,
     Quit,
     Resume,
-    GoToMainMenu
+    GoToMainMenu,
+    Settings,
+    Controls,
+    Back,
+    Rebind(InputAction),
+    DefaultBindings,
+    LeftHandedBindings,
+    Sensitivity,
+    Adjust(SettingField, f32),
+    CycleImportGame,
+    ApplyImport,
+    Crosshair,
+    EnterShareCode,
+    Audio,
+    Weapon
 }
 
 #[derive(Component)]
@@ -410,55 +695,728 @@ pub struct GameEntity;
 #[derive(Component)]
 pub struct TextEntity;
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct EntityHandler {
     pub map_handle: Option<Handle<Scene>>,
     pub gun_handle: Option<Handle<Scene>>,
-    pub crosshair_handle: Option<Handle<Image>>,
     pub target_texture_handle: Option<Handle<Image>>,
     pub text_font_handle: Option<Handle<Font>>
 }
 
-impl Default for EntityHandler {
+#[derive(Component)]
+pub struct StartButton;
+
+#[derive(Resource, Default)]
+pub struct LastState {
+    pub state: Option<GameState>
+}
+
+#[derive(Component)]
+pub struct MapImage;
+
+#[derive(Resource, Default)]
+pub struct PlayerEntity {
+    pub entity: Option<Entity>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementModel {
+    Arcade,
+    Source
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    pub deadzone: f32,
+    pub look_speed: f32,
+    pub look_curve: f32,
+    pub aim_acceleration: f32,
+    pub acceleration_delay: f32,
+    pub acceleration_ramp: f32
+}
+
+impl Default for GamepadSettings {
     fn default() -> Self {
         Self {
-            map_handle: None,
-            gun_handle: None,
-            crosshair_handle: None,
-            target_texture_handle: None,
-            text_font_handle: None
+            deadzone: 0.15,
+            look_speed: 3.5,
+            look_curve: 2.0,
+            aim_acceleration: 1.8,
+            acceleration_delay: 0.25,
+            acceleration_ramp: 0.4
         }
     }
 }
 
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub movement_model: MovementModel,
+    pub gamepad: GamepadSettings,
+    pub sensitivity: f32,
+    pub sensitivity_x: f32,
+    pub sensitivity_y: f32,
+    pub mouse_dpi: f32,
+    pub window_mode: WindowMode,
+    pub resolution: (u32, u32),
+    pub vsync: bool,
+    pub msaa_samples: u32,
+    pub fov: f32,
+    pub master_volume: f32,
+    pub ambience_volume: f32,
+    pub weapon_volume: f32,
+    pub ui_volume: f32,
+    pub mute_unfocused: bool,
+    pub viewmodel_fov: f32,
+    pub viewmodel_offset: Vec3,
+    pub left_handed: bool,
+    pub crosshair: CrosshairSettings,
+    pub damage_numbers: bool,
+    pub bullet_decals: bool,
+    pub tracers: bool,
+    pub muzzle_flash: bool,
+    pub zoom_sensitivity: f32,
+    pub ghost: bool
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            movement_model: MovementModel::Arcade,
+            gamepad: GamepadSettings::default(),
+            sensitivity: 1.0,
+            sensitivity_x: 1.0,
+            sensitivity_y: 1.0,
+            mouse_dpi: 800.0,
+            window_mode: WindowMode::BorderlessFullscreen,
+            resolution: (1920, 1080),
+            vsync: true,
+            msaa_samples: 8,
+            // horizontal fov at 4:3, wider screens see more to the sides (hor+)
+            fov: 100.0,
+            master_volume: 1.0,
+            ambience_volume: 0.3,
+            weapon_volume: 1.0,
+            ui_volume: 0.5,
+            mute_unfocused: true,
+            viewmodel_fov: 100.0,
+            viewmodel_offset: Vec3::ZERO,
+            left_handed: false,
+            crosshair: CrosshairSettings::default(),
+            damage_numbers: false,
+            bullet_decals: true,
+            tracers: true,
+            muzzle_flash: true,
+            // sensitivity multiplier while zoomed, on top of matching the narrower fov
+            zoom_sensitivity: 1.0,
+            // shows the personal best run of the level being played
+            ghost: false
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingField {
+    Movement,
+    WindowMode,
+    Resolution,
+    Vsync,
+    Msaa,
+    Fov,
+    MasterVolume,
+    AmbienceVolume,
+    WeaponVolume,
+    UiVolume,
+    MuteUnfocused,
+    ViewmodelFov,
+    ViewmodelX,
+    ViewmodelY,
+    ViewmodelZ,
+    ViewmodelHand,
+    Sensitivity,
+    SensitivityX,
+    SensitivityY,
+    MouseDpi,
+    ImportSensitivity,
+    ImportDpi,
+    CrosshairGap,
+    CrosshairLength,
+    CrosshairThickness,
+    CrosshairOutline,
+    CrosshairOutlineThickness,
+    CrosshairColor,
+    CrosshairAlpha,
+    CrosshairDot,
+    CrosshairDynamic,
+    CrosshairHitMarker,
+    DamageNumbers,
+    BulletDecals,
+    Tracers,
+    MuzzleFlash,
+    ZoomSensitivity,
+    Ghost
+}
+
 #[derive(Component)]
-pub struct StartButton;
+pub struct SettingText(pub SettingField);
 
-#[derive(Resource)]
-pub struct LastState {
-    pub state: Option<GameState>
+#[derive(Component)]
+pub struct Slider(pub SettingField);
+
+#[derive(Component)]
+pub struct SliderFill(pub SettingField);
+
+// Sizes are in ui pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrosshairSettings {
+    pub gap: f32,
+    pub length: f32,
+    pub thickness: f32,
+    pub outline: bool,
+    pub outline_thickness: f32,
+    pub color: Color,
+    pub dot: bool,
+    pub dynamic: bool,
+    pub hit_marker: bool
 }
 
-impl Default for LastState {
+impl Default for CrosshairSettings {
     fn default() -> Self {
         Self {
-            state: None
+            gap: 3.0,
+            length: 7.0,
+            thickness: 2.0,
+            outline: true,
+            outline_thickness: 1.0,
+            color: Color::rgba(0.2, 0.98, 0.2, 1.0),
+            dot: false,
+            dynamic: false,
+            hit_marker: true
         }
     }
 }
 
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrosshairPart {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    Dot,
+    HitTopLeft,
+    HitTopRight,
+    HitBottomLeft,
+    HitBottomRight
+}
+
 #[derive(Component)]
-pub struct MapImage;
+pub struct Crosshair {
+    pub spread: f32,
+    pub last_position: Option<Vec3>,
+    pub hit_marker_time: f32,
+    pub hit_marker_kill: bool
+}
 
-#[derive(Resource)]
-pub struct PlayerEntity {
-    pub entity: Option<Entity>
+impl Default for Crosshair {
+    fn default() -> Self {
+        Self {
+            spread: 0.0,
+            last_position: None,
+            hit_marker_time: 0.0,
+            hit_marker_kill: false
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ShareCodeInput {
+    pub code: String,
+    pub typing: bool,
+    pub message: Option<String>
+}
+
+#[derive(Component)]
+pub struct ShareCodeText;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportGame {
+    Cs2,
+    Valorant,
+    Overwatch
+}
+
+impl ImportGame {
+    // Degrees turned per count at sensitivity 1
+    pub fn yaw(&self) -> f32 {
+        match self {
+            ImportGame::Cs2 => 0.022,
+            ImportGame::Valorant => 0.07,
+            ImportGame::Overwatch => 0.0066
+        }
+    }
+
+    pub fn sensitivity_step(&self) -> f32 {
+        match self {
+            ImportGame::Cs2 => 0.05,
+            ImportGame::Valorant => 0.01,
+            ImportGame::Overwatch => 0.25
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportGame::Cs2 => "CS2",
+            ImportGame::Valorant => "VALORANT",
+            ImportGame::Overwatch => "OVERWATCH"
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ImportGame::Cs2 => ImportGame::Valorant,
+            ImportGame::Valorant => ImportGame::Overwatch,
+            ImportGame::Overwatch => ImportGame::Cs2
+        }
+    }
+
+    // Default sensitivity in that game, used when switching to it
+    pub fn default_sensitivity(&self) -> f32 {
+        match self {
+            ImportGame::Cs2 => 1.25,
+            ImportGame::Valorant => 0.4,
+            ImportGame::Overwatch => 4.0
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct SensitivityImport {
+    pub game: ImportGame,
+    pub sensitivity: f32,
+    pub dpi: f32
 }
 
-impl Default for PlayerEntity {
+impl Default for SensitivityImport {
     fn default() -> Self {
         Self {
-            entity: None
+            game: ImportGame::Cs2,
+            sensitivity: ImportGame::Cs2.default_sensitivity(),
+            dpi: 800.0
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Walk,
+    Sprint,
+    Crouch,
+    Fire,
+    Aim,
+    Reload,
+    Pause,
+    // replay viewer
+    SeekForward,
+    SeekBackward,
+    SpeedUp,
+    SpeedDown,
+    FreeCamera,
+    FlyUp,
+    FlyDown
+}
+
+impl InputAction {
+    // replays store the actions as bits in this order, changing it needs a new replay version
+    pub const ALL: [InputAction; 12] = [
+        InputAction::MoveForward,
+        InputAction::MoveBackward,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Jump,
+        InputAction::Walk,
+        InputAction::Sprint,
+        InputAction::Crouch,
+        InputAction::Fire,
+        InputAction::Aim,
+        InputAction::Reload,
+        InputAction::Pause
+    ];
+
+    // only read by the replay viewer, which also plays and pauses with jump and flies with the
+    // movement, sprint and aim actions. Replays don't record these
+    pub const VIEWER: [InputAction; 7] = [
+        InputAction::SeekForward,
+        InputAction::SeekBackward,
+        InputAction::SpeedUp,
+        InputAction::SpeedDown,
+        InputAction::FreeCamera,
+        InputAction::FlyUp,
+        InputAction::FlyDown
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputAction::MoveForward => "FORWARD",
+            InputAction::MoveBackward => "BACKWARD",
+            InputAction::MoveLeft => "LEFT",
+            InputAction::MoveRight => "RIGHT",
+            InputAction::Jump => "JUMP",
+            InputAction::Walk => "WALK",
+            InputAction::Sprint => "SPRINT",
+            InputAction::Crouch => "CROUCH",
+            InputAction::Fire => "FIRE",
+            InputAction::Aim => "AIM",
+            InputAction::Reload => "RELOAD",
+            InputAction::Pause => "PAUSE",
+            InputAction::SeekForward => "SEEK FORWARD",
+            InputAction::SeekBackward => "SEEK BACK",
+            InputAction::SpeedUp => "FASTER",
+            InputAction::SpeedDown => "SLOWER",
+            InputAction::FreeCamera => "FREE CAMERA",
+            InputAction::FlyUp => "FLY UP",
+            InputAction::FlyDown => "FLY DOWN"
+        }
+    }
+}
+
+// Key bindings use physical key positions, so WASD lands on ZQSD for AZERTY users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType)
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings {
+    pub actions: BTreeMap<InputAction, Vec<InputBinding>>
+}
+
+impl InputBindings {
+    pub fn left_handed() -> Self {
+        Self {
+            actions: BTreeMap::from([
+                (InputAction::MoveForward, vec![InputBinding::Key(KeyCode::ArrowUp)]),
+                (InputAction::MoveBackward, vec![InputBinding::Key(KeyCode::ArrowDown)]),
+                (InputAction::MoveLeft, vec![InputBinding::Key(KeyCode::ArrowLeft)]),
+                (InputAction::MoveRight, vec![InputBinding::Key(KeyCode::ArrowRight)]),
+                (InputAction::Jump, vec![InputBinding::Key(KeyCode::Numpad0)]),
+                (InputAction::Walk, vec![InputBinding::Key(KeyCode::ShiftRight)]),
+                (InputAction::Sprint, vec![InputBinding::Key(KeyCode::AltRight)]),
+                (InputAction::Crouch, vec![InputBinding::Key(KeyCode::ControlRight)]),
+                (InputAction::Fire, vec![InputBinding::Mouse(MouseButton::Left)]),
+                (InputAction::Aim, vec![InputBinding::Mouse(MouseButton::Right)]),
+                (InputAction::Reload, vec![InputBinding::Key(KeyCode::Delete)]),
+                (InputAction::Pause, vec![InputBinding::Key(KeyCode::Escape)]),
+                // the arrows are taken by the movement
+                (InputAction::SeekForward, vec![InputBinding::Key(KeyCode::Numpad6)]),
+                (InputAction::SeekBackward, vec![InputBinding::Key(KeyCode::Numpad4)]),
+                (InputAction::SpeedUp, vec![InputBinding::Key(KeyCode::Numpad8)]),
+                (InputAction::SpeedDown, vec![InputBinding::Key(KeyCode::Numpad2)]),
+                (InputAction::FreeCamera, vec![InputBinding::Key(KeyCode::Numpad5)]),
+                (InputAction::FlyUp, vec![InputBinding::Key(KeyCode::PageUp)]),
+                (InputAction::FlyDown, vec![InputBinding::Key(KeyCode::PageDown)])
+            ])
+        }.with_gamepad_defaults()
+    }
+
+    // Adds the gamepad buttons next to the keyboard and mouse bindings
+    fn with_gamepad_defaults(mut self) -> Self {
+        let gamepad_bindings = [
+            (InputAction::Jump, GamepadButtonType::South),
+            (InputAction::Crouch, GamepadButtonType::East),
+            (InputAction::Sprint, GamepadButtonType::LeftThumb),
+            (InputAction::Fire, GamepadButtonType::RightTrigger2),
+            (InputAction::Aim, GamepadButtonType::LeftTrigger2),
+            (InputAction::Reload, GamepadButtonType::West),
+            (InputAction::Pause, GamepadButtonType::Start)
+        ];
+        for (action, button) in gamepad_bindings {
+            self.actions.entry(action).or_default().push(InputBinding::Gamepad(button));
+        }
+        self
+    }
+
+    pub fn get(&self, action: InputAction) -> &[InputBinding] {
+        self.actions.get(&action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    // Binds an input to an action. An action already using it gets the binding it replaces in
+    // exchange, if there is none to give it the rebind is refused so no action ends up unbound
+    pub fn rebind(&mut self, action: InputAction, binding: InputBinding) -> Result<(), String> {
+        if self.get(action).contains(&binding) {
+            return Ok(());
+        }
+        // keyboard/mouse and gamepad bindings are replaced separately
+        let is_gamepad = matches!(binding, InputBinding::Gamepad(_));
+        let same_kind = |existing: &InputBinding| matches!(existing, InputBinding::Gamepad(_)) == is_gamepad;
+        let previous = self.get(action).iter().copied().find(|existing| same_kind(existing));
+
+        let other = self.actions.iter()
+            .find(|(other, bindings)| **other != action && bindings.contains(&binding))
+            .map(|(other, _)| *other);
+        if let Some(other) = other {
+            let other_bindings = self.actions.entry(other).or_default();
+            let index = other_bindings.iter().position(|existing| *existing == binding).unwrap_or_default();
+            match previous {
+                Some(previous) => other_bindings[index] = previous,
+                None if other_bindings.iter().filter(|existing| same_kind(existing)).count() > 1 => {
+                    other_bindings.remove(index);
+                }
+                None => return Err(format!("{} would be left without a binding", other.label()))
+            }
+        }
+
+        let bindings = self.actions.entry(action).or_default();
+        match bindings.iter().position(same_kind) {
+            Some(index) => bindings[index] = binding,
+            None => bindings.push(binding)
+        }
+        Ok(())
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            actions: BTreeMap::from([
+                (InputAction::MoveForward, vec![InputBinding::Key(KeyCode::KeyW)]),
+                (InputAction::MoveBackward, vec![InputBinding::Key(KeyCode::KeyS)]),
+                (InputAction::MoveLeft, vec![InputBinding::Key(KeyCode::KeyA)]),
+                (InputAction::MoveRight, vec![InputBinding::Key(KeyCode::KeyD)]),
+                (InputAction::Jump, vec![InputBinding::Key(KeyCode::Space)]),
+                (InputAction::Walk, vec![InputBinding::Key(KeyCode::ShiftLeft)]),
+                (InputAction::Sprint, vec![InputBinding::Key(KeyCode::AltLeft)]),
+                (InputAction::Crouch, vec![InputBinding::Key(KeyCode::ControlLeft)]),
+                (InputAction::Fire, vec![InputBinding::Mouse(MouseButton::Left)]),
+                (InputAction::Aim, vec![InputBinding::Mouse(MouseButton::Right)]),
+                (InputAction::Reload, vec![InputBinding::Key(KeyCode::KeyR)]),
+                (InputAction::Pause, vec![InputBinding::Key(KeyCode::Escape)]),
+                (InputAction::SeekForward, vec![InputBinding::Key(KeyCode::ArrowRight)]),
+                (InputAction::SeekBackward, vec![InputBinding::Key(KeyCode::ArrowLeft)]),
+                (InputAction::SpeedUp, vec![InputBinding::Key(KeyCode::ArrowUp)]),
+                (InputAction::SpeedDown, vec![InputBinding::Key(KeyCode::ArrowDown)]),
+                (InputAction::FreeCamera, vec![InputBinding::Key(KeyCode::KeyF)]),
+                (InputAction::FlyUp, vec![InputBinding::Key(KeyCode::KeyE)]),
+                (InputAction::FlyDown, vec![InputBinding::Key(KeyCode::KeyQ)])
+            ])
+        }.with_gamepad_defaults()
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct RebindState {
+    pub action: Option<InputAction>,
+    pub armed: bool
+}
+
+#[derive(Component)]
+pub struct BindingText(pub InputAction);
+
+// Where settings and key bindings are saved, nothing is written without one
+#[derive(Resource, Default)]
+pub struct ConfigDirectory(pub Option<PathBuf>);
+
+// What the keyboard layout prints on each physical key, learned as keys are pressed
+#[derive(Resource, Default)]
+pub struct KeyNames(pub HashMap<KeyCode, String>);
+
+#[derive(Resource, Default)]
+pub struct AnalogInput {
+    pub movement: Vec2,
+    pub look: Vec2,
+    pub look_hold_time: f32
+}
+
+#[derive(Resource, Default)]
+pub struct MenuFocus {
+    pub index: usize,
+    pub active: bool,
+    pub pressed: Option<Entity>,
+    // the highlighted button and the colour it had before
+    pub highlighted: Option<(Entity, Color)>
+}
+
+#[derive(Component)]
+pub struct ViewModelCamera;
+
+// The input of one tick, actions are bit masks in InputAction::ALL order
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReplayFrame {
+    pub pressed: u16,
+    // actions that were just pressed or just released this tick
+    pub changed: u16,
+    pub mouse: Vec2,
+    pub movement: Vec2,
+    pub look: Vec2
+}
+
+// Everything about the player and gun that carries over from before the level started
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReplayStart {
+    pub translation: Vec3,
+    pub body_velocity: Vec3,
+    pub velocity: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub stamina: f32,
+    pub crouch_amount: f32,
+    pub jump_queue: f32,
+    pub aim_amount: f32,
+    pub spread_seed: u32
+}
+
+// Where the player was and looked after a tick, and where its shot landed, for ghosts
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReplayPose {
+    pub translation: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub shot: Option<ReplayShot>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayShot {
+    pub end: Vec3,
+    pub hit: HitKind,
+    // index in the level's target positions
+    pub target_id: Option<usize>
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub level: u8,
+    pub cleared: bool,
+    // ticks spent in the playing state, the time the level took
+    pub ticks: u32,
+    pub settings: Settings,
+    pub start: ReplayStart,
+    pub frames: Vec<ReplayFrame>,
+    pub poses: Vec<ReplayPose>
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    pub current: Option<Replay>,
+    pub last: Option<Replay>,
+    // the fastest clear of every level
+    pub best: HashMap<u8, Replay>,
+    // finished replays are only kept in memory without a directory
+    pub directory: Option<PathBuf>
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub index: usize,
+    pub verified: Option<bool>
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            index: 0,
+            verified: None
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Ghost;
+
+// Replay viewer, the time is a pose index so the viewer can stop between ticks
+#[derive(Resource)]
+pub struct ReplayViewer {
+    pub replay: Replay,
+    pub tick: f32,
+    pub playing: bool,
+    pub speed: f32,
+    pub free_camera: bool,
+    pub free_position: Vec3,
+    pub free_yaw: f32,
+    pub free_pitch: f32
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            tick: 0.0,
+            playing: true,
+            speed: 1.0,
+            free_camera: false,
+            free_position: Vec3::ZERO,
+            free_yaw: 0.0,
+            free_pitch: 0.0
+        }
+    }
+}
+
+// The tick a target dies at in the replay being watched
+#[derive(Component)]
+pub struct ViewerTarget {
+    pub killed_at: Option<usize>
+}
+
+#[derive(Component)]
+pub struct ShotMarker {
+    pub tick: usize
+}
+
+// Stands in for the recorded player while flying around
+#[derive(Component)]
+pub struct ReplayBody;
+
+#[derive(Component)]
+pub struct TimelineBar;
+
+#[derive(Component)]
+pub struct TimelineFill;
+
+#[derive(Component)]
+pub struct ViewerText;
+
+// One shot of a run, the bullet goes from the camera along direction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShotRecord {
+    // seconds since the level started
+    pub time: f32,
+    pub origin: Vec3,
+    pub direction: Vec3,
+    pub hit: HitKind,
+    pub hit_entity: Option<Entity>,
+    pub target_id: Option<usize>,
+    // how far the bullet flew before hitting something
+    pub distance: Option<f32>,
+    pub velocity: Vec3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunTelemetry {
+    pub level: u8,
+    pub cleared: bool,
+    // seconds from the start of the level until it was cleared or failed
+    pub time: f32,
+    pub shots: Vec<ShotRecord>,
+    // where the player was, every POSITION_INTERVAL seconds
+    #[serde(default)]
+    pub positions: Vec<Vec3>
+}
+
+#[derive(Resource, Default)]
+pub struct TelemetryRecorder {
+    pub current: Option<RunTelemetry>,
+    pub last: Option<RunTelemetry>,
+    // the player position last tick, to tell how fast it's moving
+    pub last_position: Option<Vec3>,
+    // finished runs are only kept in memory without a directory
+    pub directory: Option<PathBuf>
+}
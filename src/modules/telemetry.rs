@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use crate::{
+    LevelState,
+    replay::level_number,
+    structs::{
+        PlayerController,
+        RunTelemetry,
+        ShotFiredEvent,
+        ShotHit,
+        ShotRecord,
+        TelemetryRecorder
+    }
+};
+use std::{
+    fmt::Write,
+    fs,
+    path::Path,
+    time::{
+        SystemTime,
+        UNIX_EPOCH
+    }
+};
+
+// How often the player position is logged for heatmaps
+pub const POSITION_INTERVAL: f32 = 0.25;
+
+const CSV_HEADER: &str = "time,origin_x,origin_y,origin_z,direction_x,direction_y,direction_z,hit,hit_entity,target_id,distance,velocity_x,velocity_y,velocity_z";
+
+// Logs every shot fired and where the player goes, a new run starts with the first tick of a level
+pub fn record_run(
+    time: Res<Time>,
+    mut shot_events: EventReader<ShotFiredEvent>,
+    mut recorder: ResMut<TelemetryRecorder>,
+    current_level: Res<State<LevelState>>,
+    player_query: Query<&Transform, With<PlayerController>>
+) {
+    let Ok(transform) = player_query.get_single() else { return };
+    let delta = time.delta_seconds();
+    // measured from the position, the player controllers keep their velocity in different units
+    let velocity = match recorder.last_position {
+        Some(last_position) if delta > 0.0 => (transform.translation - last_position) / delta,
+        _ => Vec3::ZERO
+    };
+    recorder.last_position = Some(transform.translation);
+
+    if recorder.current.is_none() {
+        let Some(level) = level_number(current_level.get()) else { return };
+        recorder.current = Some(RunTelemetry {
+            level,
+            cleared: false,
+            time: 0.0,
+            shots: Vec::new(),
+            positions: Vec::new()
+        });
+    }
+
+    let Some(run) = &mut recorder.current else { return };
+    run.time += delta;
+    if run.positions.len() as f32 * POSITION_INTERVAL <= run.time {
+        run.positions.push(transform.translation);
+    }
+    for shot_event in shot_events.read() {
+        let hit_entity = match shot_event.hit {
+            ShotHit::Nothing | ShotHit::Map => None,
+            ShotHit::StartButton(entity) | ShotHit::Target { entity, .. } => Some(entity)
+        };
+        run.shots.push(ShotRecord {
+            time: run.time,
+            origin: shot_event.camera_origin,
+            direction: shot_event.direction,
+            hit: shot_event.hit.kind(),
+            hit_entity,
+            target_id: shot_event.hit.target_id(),
+            distance: (shot_event.hit != ShotHit::Nothing).then(|| shot_event.camera_origin.distance(shot_event.end)),
+            velocity
+        });
+    }
+}
+
+// Finishes the run once the level is cleared or failed, saving it if there's a directory
+pub fn finish_run(
+    mut recorder: ResMut<TelemetryRecorder>,
+    current_level: Res<State<LevelState>>
+) {
+    // the player is moved to the next level, that jump isn't velocity
+    recorder.last_position = None;
+    let Some(mut run) = recorder.current.take() else { return };
+    if level_number(current_level.get()) == Some(run.level) {
+        recorder.current = Some(run);
+        return;
+    }
+    run.cleared = *current_level.get() != LevelState::Failed;
+
+    if let Some(directory) = &recorder.directory {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let name = format!("level{}-{}", run.level, seconds);
+        match save_run(directory, &name, &run) {
+            Ok(()) => info!("Saved {} shots of level {} to {}", run.shots.len(), run.level, directory.join(&name).display()),
+            Err(error) => warn!("{}", error)
+        }
+    }
+
+    recorder.last = Some(run);
+}
+
+// Quitting to the main menu throws away the run
+pub fn discard_run(
+    mut recorder: ResMut<TelemetryRecorder>
+) {
+    recorder.current = None;
+    recorder.last_position = None;
+}
+
+// Writes the run next to each other as <name>.csv and <name>.json
+pub fn save_run(directory: &Path, name: &str, run: &RunTelemetry) -> Result<(), String> {
+    fs::create_dir_all(directory)
+        .map_err(|error| format!("Could not create {}: {}", directory.display(), error))?;
+    for (extension, contents) in [("csv", to_csv(run)), ("json", to_json(run)?)] {
+        let path = directory.join(format!("{}.{}", name, extension));
+        fs::write(&path, contents).map_err(|error| format!("Could not write {}: {}", path.display(), error))?;
+    }
+    Ok(())
+}
+
+// One row per shot, vectors are split into a column per axis
+pub fn to_csv(run: &RunTelemetry) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for shot in &run.shots {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            shot.time,
+            shot.origin.x, shot.origin.y, shot.origin.z,
+            shot.direction.x, shot.direction.y, shot.direction.z,
+            shot.hit.name(),
+            shot.hit_entity.map_or(String::new(), |entity| entity.to_bits().to_string()),
+            shot.target_id.map_or(String::new(), |id| id.to_string()),
+            shot.distance.map_or(String::new(), |distance| distance.to_string()),
+            shot.velocity.x, shot.velocity.y, shot.velocity.z
+        );
+    }
+    csv
+}
+
+pub fn to_json(run: &RunTelemetry) -> Result<String, String> {
+    serde_json::to_string_pretty(run).map_err(|error| format!("Could not write the shot log: {}", error))
+}
+
+pub fn load_run(path: &Path) -> Result<RunTelemetry, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+    serde_json::from_str(&text).map_err(|error| format!("Could not parse {}: {}", path.display(), error))
+}
+
+// Reads every json run log in a directory, in file name order
+pub fn load_runs(directory: &Path) -> Result<Vec<RunTelemetry>, String> {
+    let entries = fs::read_dir(directory).map_err(|error| format!("Could not read {}: {}", directory.display(), error))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| load_run(path)).collect()
+}
@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use crate::{
+    game::TARGET_RADIUS,
+    navigation::{
+        MapShape,
+        NavGrid,
+        FEET
+    }
+};
+use std::collections::HashSet;
+
+// Targets closer than this to each other look like the same spot placed twice
+pub const MIN_SPACING: f32 = 1.0;
+// Targets sit around eye height, much further above the floor than this is floating
+pub const MAX_FLOOR_HEIGHT: f32 = 2.5;
+// Rough time to spot, aim at and shoot one target on top of the walking
+pub const SHOT_TIME: f32 = 0.6;
+
+pub struct LevelReport {
+    pub problems: Vec<String>,
+    // seconds to walk the shortest route found and shoot every target
+    pub route_time: Option<f32>
+}
+
+// Checks the targets of one level against the map and its time limit
+pub fn validate_level(
+    map: &MapShape,
+    grid: &NavGrid,
+    spawn_point: Vec3,
+    speed: f32,
+    positions: &[Vec3],
+    time_limit: f32
+) -> LevelReport {
+    let mut problems = Vec::new();
+    let start = grid.nearest(spawn_point - Vec3::Y * FEET);
+
+    for (index, position) in positions.iter().enumerate() {
+        let number = index + 1;
+
+        if map.distance_to_surface(*position) < TARGET_RADIUS {
+            problems.push(format!("target {} at {} is inside the map geometry", number, position));
+        }
+        if map.ray(*position, -Vec3::Y, MAX_FLOOR_HEIGHT).is_none() {
+            problems.push(format!("target {} at {} floats more than {}m above the floor", number, position, MAX_FLOOR_HEIGHT));
+        }
+        for (other_index, other) in positions.iter().enumerate().skip(index + 1) {
+            let distance = position.distance(*other);
+            if distance < MIN_SPACING {
+                problems.push(format!("targets {} and {} are only {:.2}m apart", number, other_index + 1, distance));
+            }
+        }
+        let seen = start.is_some_and(|start| grid.path_to_view(map, start, &[*position], TARGET_RADIUS, &HashSet::new()).is_some());
+        if !seen {
+            problems.push(format!("target {} at {} can not be seen from anywhere the player can walk", number, position));
+        }
+    }
+
+    let route_time = start.and_then(|start| {
+        let in_order = route_distance(map, grid, start, positions, false);
+        let nearest_first = route_distance(map, grid, start, positions, true);
+        let distance = in_order.into_iter().chain(nearest_first).reduce(f32::min)?;
+        Some(distance / speed + positions.len() as f32 * SHOT_TIME)
+    });
+    if let Some(route_time) = route_time {
+        if route_time > time_limit {
+            problems.push(format!("the route takes about {:.0}s but the limit is {:.0}s", route_time, time_limit));
+        }
+    }
+
+    LevelReport { problems, route_time }
+}
+
+// Walks from target to target, either in the order the level lists them or always to the
+// closest one left, everything in sight of a spot is shot from there
+fn route_distance(map: &MapShape, grid: &NavGrid, start: usize, positions: &[Vec3], nearest_first: bool) -> Option<f32> {
+    let mut left: Vec<Vec3> = positions.to_vec();
+    let mut current = start;
+    let mut distance = 0.0;
+
+    while !left.is_empty() {
+        let eye = grid.eye(current);
+        left.retain(|position| !map.can_see(eye, *position, TARGET_RADIUS));
+        if left.is_empty() {
+            break;
+        }
+
+        let heading_for = if nearest_first { &left[..] } else { &left[..1] };
+        let (path, length) = grid.path_to_view(map, current, heading_for, TARGET_RADIUS, &HashSet::new())?;
+        distance += length;
+        current = grid.nearest(*path.last()?)?;
+    }
+
+    Some(distance)
+}
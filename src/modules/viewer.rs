@@ -0,0 +1,443 @@
+use bevy::{
+    input::mouse::MouseMotion,
+    pbr::NotShadowCaster,
+    prelude::*,
+    ui::RelativeCursorPosition
+};
+use bevy_rapier3d::prelude::*;
+use crate::{
+    GameState,
+    LevelState,
+    input::binding_name,
+    replay::{
+        level_state,
+        TICK
+    },
+    structs::{
+        CameraController,
+        EntityHandler,
+        GameEntity,
+        HitKind,
+        InputAction,
+        InputBindings,
+        KeyNames,
+        PlayerController,
+        Replay,
+        ReplayBody,
+        ReplayPose,
+        ReplayViewer,
+        ShotMarker,
+        StartButton,
+        TargetController,
+        TimelineBar,
+        TimelineFill,
+        ViewerTarget,
+        ViewerText
+    }
+};
+
+const SEEK_SECONDS: f32 = 5.0;
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 4.0;
+const FLY_SPEED: f32 = 4.0;
+const LOOK_SENSITIVITY: f32 = 0.003;
+const CAMERA_HEIGHT: f32 = 0.65;
+const HIT_COLOR: Color = Color::rgb(0.3, 1.0, 0.3);
+const MISS_COLOR: Color = Color::rgb(1.0, 0.3, 0.2);
+
+// The first pose of the playing state, shots before it went at the start button
+pub fn playing_start(replay: &Replay) -> usize {
+    replay.poses.len().saturating_sub(replay.ticks as usize)
+}
+
+// Every shot fired while playing with the tick, where it landed and whether it hit a target
+pub fn shot_results(replay: &Replay) -> Vec<(usize, Vec3, bool)> {
+    replay.poses.iter().enumerate()
+        .skip(playing_start(replay))
+        .filter_map(|(tick, pose)| pose.shot.map(|shot| (tick, shot.end, shot.hit == HitKind::Target)))
+        .collect()
+}
+
+// The tick of the shot that killed a target, targets go down in one hit
+pub fn kill_tick(replay: &Replay, target_id: usize) -> Option<usize> {
+    replay.poses.iter()
+        .position(|pose| pose.shot.is_some_and(|shot| shot.target_id == Some(target_id)))
+}
+
+// The pose between two ticks, for watching slower than real time
+pub fn pose_at(replay: &Replay, tick: f32) -> Option<ReplayPose> {
+    let last = replay.poses.len().checked_sub(1)?;
+    let tick = tick.clamp(0.0, last as f32);
+    let before = replay.poses[tick.floor() as usize];
+    let after = replay.poses[(tick.floor() as usize + 1).min(last)];
+    let blend = tick.fract();
+
+    Some(ReplayPose {
+        translation: before.translation.lerp(after.translation, blend),
+        yaw: before.yaw + (after.yaw - before.yaw) * blend,
+        pitch: before.pitch + (after.pitch - before.pitch) * blend,
+        shot: before.shot
+    })
+}
+
+// Opens the replay from the main menu, once the app has run a frame like playback does
+pub fn start_viewer(
+    viewer: Res<ReplayViewer>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_level: ResMut<NextState<LevelState>>
+) {
+    if let Some(level) = level_state(viewer.replay.level) {
+        next_level.set(level);
+        next_state.set(GameState::Replay);
+    }
+}
+
+// Stops the physics and spawns the shot markers, the recorded player and the timeline
+pub fn enter_viewer(
+    mut commands: Commands,
+    mut rapier_configuration: ResMut<RapierConfiguration>,
+    mut viewer: ResMut<ReplayViewer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    entity_handler: Res<EntityHandler>
+) {
+    rapier_configuration.physics_pipeline_active = false;
+    viewer.tick = playing_start(&viewer.replay) as f32;
+
+    let shots = shot_results(&viewer.replay);
+    let last = viewer.replay.poses.len().saturating_sub(1).max(1) as f32;
+
+    // markers where every shot landed
+    let marker_mesh = meshes.add(Sphere { radius: 0.03 });
+    let hit_material = materials.add(StandardMaterial {
+        base_color: HIT_COLOR,
+        unlit: true,
+        ..Default::default()
+    });
+    let miss_material = materials.add(StandardMaterial {
+        base_color: MISS_COLOR,
+        unlit: true,
+        ..Default::default()
+    });
+    for (tick, end, hit) in &shots {
+        commands.spawn((
+            PbrBundle {
+                mesh: marker_mesh.clone(),
+                material: if *hit { hit_material.clone() } else { miss_material.clone() },
+                transform: Transform::from_translation(*end),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            NotShadowCaster,
+            ShotMarker { tick: *tick },
+            GameEntity
+        ));
+    }
+
+    // the recorded player, shown while flying around
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Capsule3d::new(0.1, 0.45)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1.0, 0.8, 0.3, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            }),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        NotShadowCaster,
+        ReplayBody,
+        GameEntity
+    ));
+
+    // timeline with a tick for every hit and miss
+    let text_style = TextStyle {
+        font: entity_handler.text_font_handle.clone().unwrap_or_default(),
+        font_size: 22.0,
+        ..Default::default()
+    };
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(10.0),
+                bottom: Val::Percent(4.0),
+                width: Val::Percent(80.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        GameEntity
+    )).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_sections([
+                TextSection::new("", text_style.clone()),
+                TextSection::new("", TextStyle { font_size: 16.0, ..text_style })
+            ]),
+            ViewerText
+        ));
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Px(14.0),
+                    ..Default::default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.7).into(),
+                ..Default::default()
+            },
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            TimelineBar
+        )).with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    background_color: Color::rgba(0.8, 0.8, 0.8, 0.5).into(),
+                    ..Default::default()
+                },
+                TimelineFill
+            ));
+            for (tick, _, hit) in &shots {
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Percent(*tick as f32 / last * 100.0),
+                        width: Val::Px(2.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    background_color: if *hit { HIT_COLOR.into() } else { MISS_COLOR.into() },
+                    ..Default::default()
+                });
+            }
+        });
+    });
+}
+
+pub fn exit_viewer(
+    mut commands: Commands,
+    mut rapier_configuration: ResMut<RapierConfiguration>
+) {
+    rapier_configuration.physics_pipeline_active = true;
+    commands.remove_resource::<ReplayViewer>();
+}
+
+// Play, pause, speed, seeking, the camera mode and flying around
+pub fn control_viewer(
+    actions: Res<ButtonInput<InputAction>>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    timeline_query: Query<(&Interaction, &RelativeCursorPosition), With<TimelineBar>>,
+    mut viewer: ResMut<ReplayViewer>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>
+) {
+    if actions.just_pressed(InputAction::Pause) {
+        next_state.set(GameState::MainMenu);
+        return;
+    }
+
+    let last = viewer.replay.poses.len().saturating_sub(1) as f32;
+    let delta_time = time.delta_seconds();
+
+    if actions.just_pressed(InputAction::Jump) {
+        // playing from the end starts over
+        if !viewer.playing && viewer.tick >= last {
+            viewer.tick = 0.0;
+        }
+        viewer.playing = !viewer.playing;
+    }
+    if actions.just_pressed(InputAction::SpeedUp) {
+        viewer.speed = (viewer.speed * 2.0).min(MAX_SPEED);
+    }
+    if actions.just_pressed(InputAction::SpeedDown) {
+        viewer.speed = (viewer.speed / 2.0).max(MIN_SPEED);
+    }
+    if actions.just_pressed(InputAction::SeekForward) {
+        viewer.tick = (viewer.tick + SEEK_SECONDS / TICK).min(last);
+    }
+    if actions.just_pressed(InputAction::SeekBackward) {
+        viewer.tick = (viewer.tick - SEEK_SECONDS / TICK).max(0.0);
+    }
+
+    // clicking or dragging on the timeline seeks there
+    for (interaction, cursor) in timeline_query.iter() {
+        if let (Interaction::Pressed, Some(position)) = (interaction, cursor.normalized) {
+            viewer.tick = position.x.clamp(0.0, 1.0) * last;
+        }
+    }
+
+    if viewer.playing {
+        viewer.tick += delta_time * viewer.speed / TICK;
+        if viewer.tick >= last {
+            viewer.tick = last;
+            viewer.playing = false;
+        }
+    }
+
+    if actions.just_pressed(InputAction::FreeCamera) {
+        viewer.free_camera = !viewer.free_camera;
+        // takes off from where the recorded player was looking
+        if let Some(pose) = pose_at(&viewer.replay, viewer.tick) {
+            viewer.free_position = pose.translation + Vec3::Y * CAMERA_HEIGHT;
+            viewer.free_yaw = pose.yaw;
+            viewer.free_pitch = pose.pitch;
+        }
+    }
+
+    let mouse_delta: Vec2 = mouse_motion_events.read().map(|event| event.delta).sum();
+    if !viewer.free_camera {
+        return;
+    }
+
+    if actions.pressed(InputAction::Aim) {
+        viewer.free_yaw -= mouse_delta.x * LOOK_SENSITIVITY;
+        viewer.free_pitch = (viewer.free_pitch - mouse_delta.y * LOOK_SENSITIVITY).clamp(-1.5, 1.5);
+    }
+
+    let rotation = Quat::from_axis_angle(Vec3::Y, viewer.free_yaw) * Quat::from_axis_angle(Vec3::X, viewer.free_pitch);
+    let mut direction = Vec3::ZERO;
+    for (action, step) in [
+        (InputAction::MoveForward, -Vec3::Z),
+        (InputAction::MoveBackward, Vec3::Z),
+        (InputAction::MoveLeft, -Vec3::X),
+        (InputAction::MoveRight, Vec3::X)
+    ] {
+        if actions.pressed(action) {
+            direction += rotation * step;
+        }
+    }
+    if actions.pressed(InputAction::FlyUp) {
+        direction += Vec3::Y;
+    }
+    if actions.pressed(InputAction::FlyDown) {
+        direction -= Vec3::Y;
+    }
+    let speed = if actions.pressed(InputAction::Sprint) { FLY_SPEED * 3.0 } else { FLY_SPEED };
+    viewer.free_position += direction.normalize_or_zero() * speed * delta_time;
+}
+
+// Puts the player, targets and markers where they were at the viewed tick
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn show_replay(
+    mut commands: Commands,
+    viewer: Res<ReplayViewer>,
+    mut player_query: Query<&mut Transform, (With<PlayerController>, Without<CameraController>, Without<ReplayBody>)>,
+    mut camera_query: Query<(&mut CameraController, &mut Transform), Without<PlayerController>>,
+    mut body_query: Query<(&mut Transform, &mut Visibility), (With<ReplayBody>, Without<PlayerController>, Without<CameraController>)>,
+    new_target_query: Query<(Entity, &TargetController), Without<ViewerTarget>>,
+    mut target_query: Query<(&ViewerTarget, &mut Visibility), Without<ReplayBody>>,
+    mut marker_query: Query<(&ShotMarker, &mut Visibility), (Without<ViewerTarget>, Without<ReplayBody>)>,
+    start_query: Query<Entity, With<StartButton>>
+) {
+    let Some(pose) = pose_at(&viewer.replay, viewer.tick) else { return };
+
+    let (translation, yaw, pitch) = if viewer.free_camera {
+        (viewer.free_position - Vec3::Y * CAMERA_HEIGHT, viewer.free_yaw, viewer.free_pitch)
+    } else {
+        (pose.translation, pose.yaw, pose.pitch)
+    };
+    for mut player_transform in player_query.iter_mut() {
+        player_transform.translation = translation;
+        player_transform.rotation = Quat::from_axis_angle(Vec3::Y, yaw);
+    }
+    for (mut camera, mut camera_transform) in camera_query.iter_mut() {
+        camera.yaw = yaw;
+        camera.pitch = pitch;
+        camera_transform.rotation = Quat::from_axis_angle(Vec3::X, pitch);
+    }
+
+    for (mut body_transform, mut visibility) in body_query.iter_mut() {
+        body_transform.translation = pose.translation + Vec3::Y * 0.225;
+        body_transform.rotation = Quat::from_axis_angle(Vec3::Y, pose.yaw);
+        *visibility = if viewer.free_camera { Visibility::Inherited } else { Visibility::Hidden };
+    }
+
+    // the run started from the start button already shot
+    for entity in start_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (entity, target) in new_target_query.iter() {
+        commands.entity(entity).insert(ViewerTarget {
+            killed_at: kill_tick(&viewer.replay, target.id)
+        });
+    }
+    for (target, mut visibility) in target_query.iter_mut() {
+        let alive = target.killed_at.map_or(true, |killed_at| viewer.tick < killed_at as f32);
+        *visibility = if alive { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    for (marker, mut visibility) in marker_query.iter_mut() {
+        *visibility = if viewer.tick >= marker.tick as f32 { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+pub fn update_timeline(
+    viewer: Res<ReplayViewer>,
+    bindings: Res<InputBindings>,
+    key_names: Res<KeyNames>,
+    mut fill_query: Query<&mut Style, With<TimelineFill>>,
+    mut text_query: Query<&mut Text, With<ViewerText>>
+) {
+    let replay = &viewer.replay;
+    let last = replay.poses.len().saturating_sub(1).max(1) as f32;
+    for mut style in fill_query.iter_mut() {
+        style.width = Val::Percent(viewer.tick / last * 100.0);
+    }
+
+    // times count from when the level started playing, like the level timer
+    let time = (viewer.tick - playing_start(replay) as f32).max(0.0) * TICK;
+    let total = replay.ticks as f32 * TICK;
+    let result = if replay.cleared { "CLEARED" } else { "FAILED" };
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "LEVEL {} {} - {} {:.2}x - {:.1}s / {:.1}s - {}",
+            replay.level,
+            result,
+            if viewer.playing { "PLAYING" } else { "PAUSED" },
+            viewer.speed,
+            time,
+            total,
+            if viewer.free_camera { "FREE CAMERA" } else { "FIRST PERSON" }
+        );
+        text.sections[1].value = help_text(&bindings, &key_names, viewer.free_camera);
+    }
+}
+
+// The controls with the keys they are bound to
+fn help_text(bindings: &InputBindings, key_names: &KeyNames, free_camera: bool) -> String {
+    let name = |actions: &[InputAction]| actions.iter()
+        .map(|action| bindings.get(*action).first().map_or("-".to_string(), |binding| binding_name(binding, key_names)))
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let mut controls = vec![
+        (name(&[InputAction::Jump]), "PLAY/PAUSE"),
+        (name(&[InputAction::SeekBackward, InputAction::SeekForward]), "SEEK"),
+        (name(&[InputAction::SpeedDown, InputAction::SpeedUp]), "SPEED"),
+        (name(&[InputAction::FreeCamera]), "CAMERA")
+    ];
+    if free_camera {
+        controls.push((name(&[
+            InputAction::MoveForward,
+            InputAction::MoveLeft,
+            InputAction::MoveBackward,
+            InputAction::MoveRight,
+            InputAction::FlyDown,
+            InputAction::FlyUp
+        ]), "FLY"));
+        controls.push((format!("HOLD {}", name(&[InputAction::Aim])), "TO LOOK"));
+    }
+    controls.push((name(&[InputAction::Pause]), "QUIT"));
+
+    let help: Vec<String> = controls.into_iter().map(|(keys, control)| format!("{} {}", keys, control)).collect();
+    format!("\n{}", help.join("  "))
+}
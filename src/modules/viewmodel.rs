@@ -0,0 +1,108 @@
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*
+};
+use crate::{
+    settings::viewmodel_position,
+    structs::{
+        AnalogInput,
+        GunController,
+        PlayerController,
+        Settings,
+        ShotFiredEvent,
+        ViewModelAnimation
+    }
+};
+use std::f32::consts::PI;
+
+const DRAW_TIME: f32 = 0.4;
+
+// Plays the draw animation when the weapon is brought out
+pub fn start_draw(
+    mut animation_query: Query<&mut ViewModelAnimation>
+) {
+    for mut animation in animation_query.iter_mut() {
+        animation.draw_time = 0.0;
+    }
+}
+
+// Moves the view model with walking bob, mouse sway, recoil, reloading and drawing
+#[allow(clippy::too_many_arguments)]
+pub fn animate_view_model(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    player_controller: Res<PlayerController>,
+    gun_resource: Res<GunController>,
+    analog_input: Res<AnalogInput>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    mut shot_events: EventReader<ShotFiredEvent>,
+    player_query: Query<(&GlobalTransform, &PlayerController)>,
+    mut gun_query: Query<(&GunController, &mut ViewModelAnimation, &mut Transform)>
+) {
+    let delta_time = time.delta_seconds();
+    if delta_time <= 0.0 {
+        return;
+    }
+
+    let mouse_delta: Vec2 = mouse_motion_events.read().map(|event| event.delta).sum();
+    let look_delta = mouse_delta + analog_input.look * Vec2::new(1.0, -1.0) * 20.0;
+    let shots = shot_events.read().count();
+    let player = player_query.get_single().ok();
+
+    for (gun_controller, mut animation, mut transform) in gun_query.iter_mut() {
+        // bob follows how fast the player moves along the ground
+        let mut speed = 0.0;
+        let mut target_bob = 0.0;
+        if let Some((player_transform, player)) = player {
+            let position = player_transform.translation();
+            if let Some(last_position) = animation.last_position {
+                if player.is_grounded {
+                    speed = (position - last_position).xz().length() / delta_time;
+                    target_bob = (speed / player.speed).min(1.5);
+                }
+            }
+            animation.last_position = Some(position);
+        }
+        animation.bob_amount += (target_bob - animation.bob_amount) * (8.0 * delta_time).min(1.0);
+        animation.bob_phase = (animation.bob_phase + speed * 3.0 * delta_time) % (2.0 * PI);
+
+        // sway lags behind the camera when turning
+        let target_sway = (-look_delta * 0.0005).clamp(Vec2::splat(-0.03), Vec2::splat(0.03));
+        let sway_step = (target_sway - animation.sway) * (10.0 * delta_time).min(1.0);
+        animation.sway += sway_step;
+
+        // every shot kicks the gun back and up, then it settles
+        animation.recoil = (animation.recoil + shots as f32).min(3.0);
+        animation.recoil *= (-12.0 * delta_time).exp();
+
+        animation.draw_time = (animation.draw_time + delta_time).min(DRAW_TIME);
+        let draw = 1.0 - (animation.draw_time / DRAW_TIME);
+        let draw = draw * draw;
+
+        let reload = gun_controller.reload_timer.as_ref()
+            .map_or(0.0, |reload_timer| (reload_timer.fraction() * PI).sin());
+
+        // aiming brings the sights to the middle of the screen and steadies the gun
+        let aim = gun_controller.aim_amount;
+        let steadiness = 1.0 - 0.8 * aim;
+
+        let bob = Vec3::new(
+            animation.bob_phase.sin() * 0.008,
+            -animation.bob_phase.cos().abs() * 0.01,
+            0.0
+        ) * animation.bob_amount * steadiness;
+        let sway = Vec3::new(animation.sway.x, -animation.sway.y, 0.0) * steadiness;
+        let kick = Vec3::new(0.0, 0.004, 0.025) * animation.recoil;
+        let lowered = Vec3::new(0.0, -0.12 * reload - 0.25 * draw, 0.0);
+
+        let position = viewmodel_position(&player_controller, &settings).lerp(gun_controller.sight_offset, aim);
+        transform.translation = position + bob + sway + kick + lowered;
+
+        // the model is flipped around once it has loaded
+        let base_rotation = if gun_resource.is_rotated { Quat::from_rotation_y(PI) } else { Quat::IDENTITY };
+        transform.rotation = Quat::from_rotation_x(0.05 * animation.recoil - 0.6 * draw)
+            * Quat::from_rotation_z(0.5 * reload)
+            * Quat::from_rotation_y(animation.sway.x * 2.0 * steadiness)
+            * base_rotation;
+    }
+}
@@ -0,0 +1,405 @@
+use bevy::{
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    prelude::*,
+    transform::{
+        systems::{
+            propagate_transforms,
+            sync_simple_transforms
+        },
+        TransformSystem
+    }
+};
+use bevy_rapier3d::prelude::*;
+use bevy_scene_hook::HookPlugin;
+use std::time::Duration;
+use crate::{
+    GameState,
+    LevelState,
+    MenuState,
+    structs::{
+        PlayerController,
+        MapController,
+        GunController,
+        AudioController,
+        CubemapController,
+        LevelController,
+        EntityHandler,
+        TargetController,
+        TimeController,
+        LastState,
+        PlayerEntity,
+        Settings,
+        InputAction,
+        InputBindings,
+        RebindState,
+        KeyNames,
+        ConfigDirectory,
+        AnalogInput,
+        MenuFocus,
+        SensitivityImport,
+        ShareCodeInput,
+        TargetHitEvent,
+        ShotFiredEvent,
+        BulletImpactEvent,
+        WorldSoundEvent,
+        EffectsController,
+        ReplayRecorder,
+        ReplayPlayback,
+        ReplayViewer,
+        TelemetryRecorder
+    },
+    game,
+    controls,
+    audio,
+    gunplay,
+    menu,
+    input,
+    gamepad,
+    settings,
+    crosshair,
+    effects,
+    viewmodel,
+    replay,
+    viewer,
+    telemetry,
+    lighting,
+    entities
+};
+
+// States, settings, input, the player and the level flow, every other plugin builds on this one.
+// The game is simulated in FixedUpdate at one replay tick per step, physics steps after the game
+// systems of every tick
+pub struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<RapierPhysicsPlugin<NoUserData>>() {
+            app
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::Fixed {
+                    dt: replay::TICK,
+                    substeps: 1
+                },
+                ..Default::default()
+            })
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_schedule(FixedPostUpdate));
+        }
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+
+        app
+        .insert_resource(Time::<Fixed>::from_duration(Duration::from_secs_f32(replay::TICK)))
+        .init_state::<GameState>()
+        .init_state::<LevelState>()
+        // the binary inserts the settings and bindings loaded from the config files before this
+        .init_resource::<Settings>()
+        .init_resource::<PlayerController>()
+        .init_resource::<TargetController>()
+        .init_resource::<TimeController>()
+        .init_resource::<MapController>()
+        .init_resource::<LevelController>()
+        .init_resource::<EntityHandler>()
+        .init_resource::<LevelState>()
+        .init_resource::<LastState>()
+        .init_resource::<PlayerEntity>()
+        .init_resource::<InputBindings>()
+        .init_resource::<ButtonInput<InputAction>>()
+        .init_resource::<AnalogInput>()
+        .init_resource::<KeyNames>()
+        .add_event::<WorldSoundEvent>()
+        // states change and input is read once per tick, so a tick plays out the same however
+        // many of them a frame runs
+        .add_systems(FixedPreUpdate, (
+            apply_state_transition::<GameState>,
+            apply_state_transition::<LevelState>,
+            (input::update_actions, gamepad::update_sticks)
+        ).chain())
+        .add_systems(PreUpdate, input::learn_key_names.after(bevy::input::InputSystem))
+        // the next tick moves from where physics left the bodies
+        .add_systems(FixedPostUpdate, (sync_simple_transforms, propagate_transforms).in_set(TransformSystem::TransformPropagate))
+        // game
+        .add_systems(OnTransition {
+            from: GameState::MainMenu,
+            to: GameState::Start
+        }, game::setup)
+        .add_systems(OnEnter(LevelState::Level1), game::initiate_level)
+        .add_systems(OnEnter(LevelState::Level2), game::initiate_level)
+        .add_systems(OnEnter(LevelState::Level3), game::initiate_level)
+        .add_systems(OnEnter(LevelState::Level4), game::initiate_level)
+        .add_systems(OnEnter(LevelState::Level5), game::initiate_level)
+        .add_systems(OnEnter(LevelState::Failed), entities::despawn_targets)
+        .add_systems(OnEnter(LevelState::NoLevel), entities::despawn_targets)
+        // a tick turns the camera, then moves the player, then shoots, every step ordered so it
+        // plays out the same each time
+        .add_systems(FixedUpdate, (
+            game::update,
+            (game::mouse_callback, gamepad::look).chain()
+        ).run_if(game::in_game_state))
+        .add_systems(Update, game::diagnostics.run_if(game::in_game_state))
+        .add_systems(FixedUpdate, (
+            game::change_level_state.before(game::update),
+            game::update_level_timer.before(game::update)
+        ).run_if(game::in_playing_state))
+        .add_systems(FixedUpdate, controls::update.after(gamepad::look).run_if(game::in_playing_state.or_else(game::in_won_state)))
+        // cleanup systems
+        .add_systems(OnEnter(GameState::Playing), entities::despawn_text_entities)
+        .add_systems(OnTransition {
+            from: GameState::PauseMenu,
+            to: GameState::MainMenu
+        }, entities::despawn_game_entities)
+        // cursor
+        .add_systems(OnTransition {
+            from: GameState::PauseMenu,
+            to: GameState::Playing
+        }, game::change_cursor_state)
+        .add_systems(OnTransition {
+            from: GameState::PauseMenu,
+            to: GameState::Start
+        }, game::change_cursor_state)
+        .add_systems(OnTransition {
+            from: GameState::PauseMenu,
+            to: GameState::Won
+        }, game::change_cursor_state);
+    }
+}
+
+// Shooting, reloading, aiming and what happens to hit targets, owns the shot and hit events. The
+// shots are simulated in FixedUpdate, the feedback reads their events in Update
+pub struct GunplayPlugin;
+
+impl Plugin for GunplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<GunController>()
+        .add_event::<TargetHitEvent>()
+        .add_event::<ShotFiredEvent>()
+        .add_event::<BulletImpactEvent>()
+        .add_systems(FixedUpdate, gunplay::update.after(controls::update).before(game::update).run_if(game::in_start_state.or_else(game::in_playing_state)))
+        .add_systems(FixedUpdate, (
+            gunplay::reload.before(gunplay::update),
+            gunplay::aim.after(gunplay::reload).after(gamepad::look).before(controls::update)
+        ).run_if(game::in_game_state))
+        // hit feedback
+        .add_systems(FixedUpdate, gunplay::destroy_targets.after(gunplay::update).before(game::change_level_state))
+        .add_systems(Update, (gunplay::spawn_damage_numbers, gunplay::animate_dying, gunplay::update_damage_numbers))
+        // shot log for coaching
+        .init_resource::<TelemetryRecorder>()
+        .add_systems(FixedUpdate, (
+            telemetry::finish_run.run_if(state_changed::<LevelState>),
+            telemetry::record_run.after(gunplay::update)
+        ).chain().run_if(game::in_game_state))
+        .add_systems(OnTransition {
+            from: GameState::PauseMenu,
+            to: GameState::MainMenu
+        }, telemetry::discard_run);
+    }
+}
+
+// Gunshots, hit sounds, ambience and the mixer, on top of the gameplay and gunplay plugins
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<AudioController>()
+        .add_systems(Startup, audio::load_audio)
+        .add_systems(OnEnter(LevelState::Level1), audio::select_ambience)
+        .add_systems(OnEnter(LevelState::Level2), audio::select_ambience)
+        .add_systems(OnEnter(LevelState::Level3), audio::select_ambience)
+        .add_systems(OnEnter(LevelState::Level4), audio::select_ambience)
+        .add_systems(OnEnter(LevelState::Level5), audio::select_ambience)
+        .add_systems(Update, audio::audio_playback.run_if(game::in_start_state.or_else(game::in_playing_state)))
+        .add_systems(Update, (audio::play_hit_sounds, audio::play_world_sounds))
+        // mixer
+        .add_systems(Update, (
+            audio::track_focus,
+            audio::update_ambience,
+            audio::update_mixer,
+            audio::play_ui_sounds
+        ).chain());
+    }
+}
+
+// The map, player model, lighting, hud and shooting effects, on top of the gameplay and gunplay
+// plugins
+pub struct RenderingPlugin;
+
+impl Plugin for RenderingPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<HookPlugin>() {
+            app.add_plugins(HookPlugin);
+        }
+
+        app
+        .init_resource::<CubemapController>()
+        .init_resource::<EffectsController>()
+        .add_systems(Startup, (entities::load_entities, effects::load_effects))
+        .add_systems(Update, settings::apply_settings.run_if(resource_changed::<Settings>))
+        .add_systems(Update, settings::update_camera_fov)
+        // game
+        .add_systems(OnTransition {
+            from: GameState::MainMenu,
+            to: GameState::Start
+        }, (
+            entities::setup,
+            lighting::setup
+        ))
+        .add_systems(OnTransition {
+            from: GameState::MainMenu,
+            to: GameState::Replay
+        }, (
+            entities::setup,
+            lighting::setup
+        ))
+        .add_systems(Update, (
+            entities::rotate_map,
+            entities::rotate_gun,
+            entities::load_cubemap
+        ).run_if(game::in_start_state.or_else(game::in_playing_state).or_else(game::in_replay_state)))
+        // crosshair and hit feedback
+        .add_systems(Update, crosshair::update_crosshair)
+        .add_systems(Update, crosshair::show_hit_marker.before(crosshair::update_crosshair))
+        // shooting effects
+        .add_systems(Update, (
+            effects::spawn_decals,
+            effects::spawn_tracers,
+            effects::flash_muzzle
+        ))
+        .add_systems(Update, (effects::update_decals, effects::update_tracers, effects::update_muzzle_flash))
+        .add_systems(Update, effects::update_ghost.run_if(game::in_game_state))
+        // view model
+        .add_systems(OnEnter(GameState::Start), viewmodel::start_draw)
+        .add_systems(Update, (
+            crosshair::update_scope_overlay,
+            viewmodel::animate_view_model
+        ).run_if(game::in_game_state))
+        // text systems
+        .add_systems(OnEnter(GameState::Start), entities::spawn_start_text)
+        .add_systems(OnEnter(LevelState::Failed), entities::spawn_fail_text)
+        .add_systems(OnEnter(GameState::Won), entities::spawn_win_text);
+    }
+}
+
+// The main, pause and settings menus, on top of the gameplay plugin's states, settings and bindings
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_state::<MenuState>()
+        .init_resource::<RebindState>()
+        .init_resource::<ConfigDirectory>()
+        .init_resource::<MenuFocus>()
+        .init_resource::<SensitivityImport>()
+        .init_resource::<ShareCodeInput>()
+        // main menu
+        .add_systems(OnEnter(GameState::MainMenu), menu::setup_main_menu)
+        .add_systems(OnExit(GameState::MainMenu), entities::despawn_menu_entities)
+        // pause menu
+        .add_systems(OnEnter(GameState::PauseMenu), menu::setup_pause_menu)
+        .add_systems(OnExit(GameState::PauseMenu), entities::despawn_menu_entities)
+        // added once for both menus, other menu systems are ordered against it
+        .add_systems(Update, (
+            gamepad::navigate_menu.before(menu::menu_interactions),
+            menu::menu_interactions
+        ).run_if(game::in_main_menu_state.or_else(game::in_pause_menu_state)))
+        // settings menu
+        .add_systems(OnEnter(MenuState::Settings), (menu::despawn_menu_nodes, menu::setup_settings_menu).chain())
+        // every settings menu saves when it closes, and quitting saves from wherever it happens
+        .add_systems(OnExit(MenuState::Settings), settings::save_settings_on_exit)
+        .add_systems(OnExit(MenuState::Sensitivity), settings::save_settings_on_exit)
+        .add_systems(OnExit(MenuState::Crosshair), settings::save_settings_on_exit)
+        .add_systems(OnExit(MenuState::Audio), settings::save_settings_on_exit)
+        .add_systems(OnExit(MenuState::Weapon), settings::save_settings_on_exit)
+        .add_systems(Last, settings::save_settings_on_quit)
+        .add_systems(OnTransition {
+            from: MenuState::Settings,
+            to: MenuState::Root
+        }, (menu::despawn_menu_nodes, menu::return_to_root_menu).chain())
+        .add_systems(Update, menu::update_setting_text.run_if(in_state(MenuState::Settings)))
+        // controls menu
+        .add_systems(OnEnter(MenuState::Controls), (menu::despawn_menu_nodes, menu::setup_controls_menu).chain())
+        .add_systems(Update, (
+            menu::update_binding_text,
+            input::capture_rebind.after(menu::menu_interactions)
+        ).run_if(in_state(MenuState::Controls)))
+        // sensitivity menu
+        .add_systems(OnEnter(MenuState::Sensitivity), (menu::despawn_menu_nodes, menu::setup_sensitivity_menu).chain())
+        .add_systems(Update, menu::update_setting_text.run_if(in_state(MenuState::Sensitivity)))
+        // crosshair menu
+        .add_systems(OnEnter(MenuState::Crosshair), (menu::despawn_menu_nodes, menu::setup_crosshair_menu).chain())
+        .add_systems(Update, (
+            menu::update_setting_text,
+            crosshair::update_share_code_text,
+            crosshair::capture_share_code.after(menu::menu_interactions)
+        ).run_if(in_state(MenuState::Crosshair)))
+        // audio menu
+        .add_systems(OnEnter(MenuState::Audio), (menu::despawn_menu_nodes, menu::setup_audio_menu).chain())
+        .add_systems(Update, (
+            menu::update_setting_text,
+            menu::drag_sliders.after(menu::menu_interactions),
+            menu::update_sliders
+        ).run_if(in_state(MenuState::Audio)))
+        // weapon menu
+        .add_systems(OnEnter(MenuState::Weapon), (menu::despawn_menu_nodes, menu::setup_weapon_menu).chain())
+        .add_systems(Update, menu::update_setting_text.run_if(in_state(MenuState::Weapon)));
+    }
+}
+
+
+// Records every level to a replay and plays one back when a replay is inserted, one frame per
+// fixed tick
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<ReplayRecorder>()
+        .add_systems(Startup, replay::load_best_replays)
+        .add_systems(Update, replay::start_playback.run_if(resource_exists::<ReplayPlayback>.and_then(game::in_main_menu_state)))
+        // runs ahead of everything that reads input, so playback input replaces the live input
+        .add_systems(FixedUpdate, (
+            replay::finish_recording.run_if(state_changed::<LevelState>),
+            replay::play_frame.run_if(resource_exists::<ReplayPlayback>),
+            replay::record_frame
+        ).chain()
+        .run_if(game::in_game_state)
+        .before(game::update)
+        .before(game::mouse_callback)
+        .before(gamepad::look)
+        .before(controls::update)
+        .before(gunplay::update)
+        .before(gunplay::aim)
+        .before(gunplay::reload))
+        .add_systems(FixedUpdate, replay::record_pose.after(gunplay::update).run_if(game::in_game_state))
+        .add_systems(OnTransition {
+            from: GameState::PauseMenu,
+            to: GameState::MainMenu
+        }, replay::discard_recording);
+    }
+}
+
+// Watching replays with a timeline, the map and targets are set up like a level with no input
+pub struct ViewerPlugin;
+
+impl Plugin for ViewerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Update, viewer::start_viewer.run_if(resource_exists::<ReplayViewer>.and_then(game::in_main_menu_state)))
+        .add_systems(OnEnter(GameState::Replay), viewer::enter_viewer)
+        // the controls read the actions of each tick, what they show follows every frame
+        .add_systems(FixedUpdate, viewer::control_viewer.run_if(game::in_replay_state))
+        .add_systems(Update, (
+            viewer::show_replay,
+            viewer::update_timeline
+        ).chain().run_if(game::in_replay_state))
+        .add_systems(OnTransition {
+            from: GameState::Replay,
+            to: GameState::MainMenu
+        }, (
+            entities::despawn_game_entities,
+            viewer::exit_viewer
+        ));
+    }
+}
\ No newline at end of file
@@ -12,8 +12,22 @@ use crate::{
         GameEntity,
         TextEntity,
         EntityHandler,
-        PlayerEntity
-    }
+        PlayerEntity,
+        Settings,
+        ViewModelCamera,
+        ViewModelAnimation,
+        EffectsController
+    },
+    settings,
+    gunplay::{
+        random_seed,
+        MUZZLE_OFFSET
+    },
+    crosshair::{
+        spawn_crosshair,
+        spawn_scope_overlay
+    },
+    effects::spawn_muzzle_flash
 };
 use bevy_scene_hook::{
     HookedSceneBundle,
@@ -24,8 +38,12 @@ use bevy::{
     asset::LoadState,
     core_pipeline::Skybox,
     render::{
+        camera::ClearColorConfig,
         texture::CompressedImageFormats,
-        view::NoFrustumCulling,
+        view::{
+            NoFrustumCulling,
+            RenderLayers
+        },
         render_resource::{
             TextureViewDescriptor,
             TextureViewDimension
@@ -40,14 +58,12 @@ pub fn load_entities(
 ) {
     let map_handle = asset_server.load("de_dust2.glb#Scene0");
     let gun_handle = asset_server.load("cs1.6_ak-47.glb#Scene0");
-    let crosshair_handle = asset_server.load("textures/crosshair.png");
     let target_texture_handle = asset_server.load("textures/default_texture.png");
     let text_font_handle = asset_server.load("fonts/JetBrainsMonoNLNerdFont-Regular.ttf");
 
     commands.insert_resource(EntityHandler {
         map_handle: Some(map_handle),
         gun_handle: Some(gun_handle),
-        crosshair_handle: Some(crosshair_handle),
         target_texture_handle: Some(target_texture_handle),
         text_font_handle: Some(text_font_handle)
     });
@@ -59,7 +75,9 @@ pub fn setup(
     asset_server: Res<AssetServer>,
     entity_handler: Res<EntityHandler>,
     player_controller: Res<PlayerController>,
-    mut player_entity: ResMut<PlayerEntity>
+    mut player_entity: ResMut<PlayerEntity>,
+    settings: Res<Settings>,
+    effects: Res<EffectsController>
 ) {
     //skybox
     const CUBEMAP: &[(&str, CompressedImageFormats)] = &[
@@ -84,12 +102,7 @@ pub fn setup(
     // player
     let player_entity_id = commands.spawn((
         PlayerController { ..Default::default() },
-        RigidBody::Dynamic,
-        GravityScale(0.9),
-        Sleeping::disabled(),
-        Collider::capsule(Vec3::ZERO, Vec3::new(0.0, 0.450, 0.0), 0.1),
-        LockedAxes::ROTATION_LOCKED,
-        Ccd { enabled: true },
+        player_body(&player_controller),
         VisibilityBundle::default(),
         GameEntity
     ))
@@ -98,77 +111,90 @@ pub fn setup(
             Camera3dBundle {
                 transform: Transform::from_translation(Vec3::new(0.0, 0.650, 0.0)),
                 projection: Projection::Perspective(PerspectiveProjection {
-                    fov: std::f32::consts::FRAC_PI_2 - 0.1,
+                    fov: settings::camera_fov(&settings, 1.0),
                     near: 0.01,
                     ..Default::default()
                 }),
                 ..Default::default()
             },
             CameraController::default(),
+            // ears are spread wider than a head so panning is noticeable at the audio spatial scale
+            SpatialListener::new(2.0),
             Skybox {
                 image: skybox_handle.clone(),
                 brightness: 1000.0
             },
             VisibilityBundle::default()
         )).with_children(|parent| {
+            // draws the gun on top of the world with its own fov
+            parent.spawn((
+                Camera3dBundle {
+                    camera: Camera {
+                        order: 1,
+                        clear_color: ClearColorConfig::None,
+                        ..Default::default()
+                    },
+                    projection: Projection::Perspective(PerspectiveProjection {
+                        fov: settings::vertical_fov(settings.viewmodel_fov),
+                        near: 0.01,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                RenderLayers::layer(1),
+                ViewModelCamera
+            ));
+            spawn_muzzle_flash(
+                parent,
+                &effects,
+                settings::viewmodel_position(&player_controller, &settings) + MUZZLE_OFFSET
+            );
             parent.spawn((
                 HookedSceneBundle {
                     scene: SceneBundle {
                         scene: entity_handler.gun_handle.clone().expect(""),
-                        transform: Transform::from_translation(player_controller.view_model),
+                        transform: Transform::from_translation(settings::viewmodel_position(&player_controller, &settings)),
                         ..Default::default()
                     },
                     hook: SceneHook::new(|entity, commands| {
                         if entity.get::<Handle<Mesh>>().is_some() {
-                            commands.insert(NoFrustumCulling);
+                            commands.insert((NoFrustumCulling, RenderLayers::layer(1)));
                         }
                     })
                 },
                 GunController {
                     shooting: false,
-                    bullet_delay: Some(Timer::from_seconds(0.1, TimerMode::Repeating)),
                     just_pressed: false,
                     is_rotated: false,
                     model_handle: entity_handler.gun_handle.clone(),
-                    play_audio: false
+                    spread_seed: random_seed(),
+                    ..Default::default()
                 },
+                ViewModelAnimation::default()
             ));
         });
         })
-        .insert(
-            TransformBundle::from(
-                Transform::from_xyz(
-                    player_controller.spawn_point.x,
-                    player_controller.spawn_point.y,
-                    player_controller.spawn_point.z
-                )
-            )
-        ).id();
+        .id();
 
     // sets the player entity
     player_entity.entity = Some(player_entity_id);
 
     // crosshair
-    commands.spawn(ImageBundle {
-        image: UiImage {
-            texture: entity_handler.crosshair_handle.clone().expect(""),
-            ..default()
-        },
-        style: Style {
-            position_type: PositionType::Absolute,
-            width: Val::Px(24.0),
-            height: Val::Px(24.0),
-            left: Val::Percent(50.0),
-            bottom: Val::Percent(50.0),
-            margin: UiRect {
-                left: Val::Px(-12.0),
-                bottom: Val::Px(-12.0),
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
                 ..default()
             },
             ..default()
         },
-        ..default()
-    }).insert(GameEntity);
+        GameEntity
+    )).with_children(|parent| {
+        spawn_scope_overlay(parent);
+        spawn_crosshair(parent);
+    });
 
     commands.insert_resource(MapController {
         is_rotated: false,
@@ -177,11 +203,10 @@ pub fn setup(
 
     commands.insert_resource(GunController {
         shooting: false,
-        bullet_delay: Some(Timer::from_seconds(0.1, TimerMode::Repeating)),
         just_pressed: false,
         is_rotated: false,
         model_handle: entity_handler.gun_handle.clone(),
-        play_audio: false
+        ..Default::default()
     });
 
     commands.insert_resource(CubemapController {
@@ -190,6 +215,21 @@ pub fn setup(
     });
 }
 
+// The player's physics body at the spawn point, also used by the headless app
+pub fn player_body(player_controller: &PlayerController) -> impl Bundle {
+    (
+        RigidBody::Dynamic,
+        GravityScale(0.9),
+        Sleeping::disabled(),
+        // read and written by replays
+        Velocity::zero(),
+        Collider::capsule(Vec3::ZERO, Vec3::new(0.0, 0.450, 0.0), 0.1),
+        LockedAxes::ROTATION_LOCKED,
+        Ccd { enabled: true },
+        TransformBundle::from(Transform::from_translation(player_controller.spawn_point))
+    )
+}
+
 // Spawns the start text
 pub fn spawn_start_text(
     mut commands: Commands,
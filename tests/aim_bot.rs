@@ -0,0 +1,562 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use game::{
+    GameState,
+    LevelState,
+    headless::{
+        headless_app,
+        set_view,
+        view_angles,
+        TICK
+    },
+    game::TARGET_RADIUS,
+    gunplay::next_random,
+    navigation::{
+        MapShape,
+        NavGrid,
+        CELL,
+        FEET,
+        VIEW_RANGE
+    },
+    structs::{
+        CameraController,
+        LevelController,
+        MapCollider,
+        PlayerController,
+        PlayerEntity,
+        StartButton,
+        TargetController,
+        TimeController
+    }
+};
+use std::{
+    collections::{
+        HashMap,
+        HashSet
+    },
+    f32::consts::PI
+};
+use common::{
+    game_state,
+    level_state,
+    start_game
+};
+
+// How well the bot plays, the defaults are meant to be a decent human
+#[derive(Debug, Clone, Copy)]
+struct Profile {
+    name: &'static str,
+    // seconds between a target showing up and starting to turn towards it
+    reaction_time: f32,
+    // fastest turn in radians per second
+    flick_speed: f32,
+    // how far off each flick can land, in radians
+    aim_error: f32,
+    seed: u32
+}
+
+const HUMAN: Profile = Profile {
+    name: "human",
+    reaction_time: 0.25,
+    flick_speed: 8.0,
+    aim_error: 0.008,
+    seed: 0x2545f491
+};
+
+// What the human profile manages on each level, level 4 can't be cleared in time yet. Changes to
+// the levels, movement or shooting that move these have to update them on purpose
+const KNOWN_RESULTS: [(LevelState, Known); 5] = [
+    (LevelState::Level1, Known::Beaten(26.7)),
+    (LevelState::Level2, Known::Beaten(23.7)),
+    (LevelState::Level3, Known::Beaten(47.4)),
+    (LevelState::Level4, Known::TimedOut(1)),
+    (LevelState::Level5, Known::Beaten(87.8))
+];
+// How far a beaten time may drift before it counts as a different result
+const TIME_TOLERANCE: f32 = 0.5;
+// Misses on one target before looking for a better spot to shoot it from
+const GIVE_UP_SHOTS: u32 = 6;
+
+#[derive(Debug, Clone, Copy)]
+enum Known {
+    Beaten(f32),
+    TimedOut(usize)
+}
+
+#[derive(Debug)]
+enum Outcome {
+    Beaten { time: f32 },
+    TimedOut { targets_left: usize },
+    Stuck { targets_left: usize }
+}
+
+// Keeps an angle difference between -PI and PI
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+// What the bot sees each frame
+struct View {
+    player: Entity,
+    eye: Vec3,
+    feet: Vec3,
+    yaw: f32,
+    pitch: f32,
+    targets: Vec<(Entity, Vec3)>
+}
+
+// Plays the game through the same inputs a player has, mouse look and key presses
+struct Bot {
+    profile: Profile,
+    route: Vec<Vec3>,
+    seed: u32,
+    clock: f32,
+    first_seen: HashMap<Entity, f32>,
+    target: Option<Entity>,
+    aim_offset: Vec2,
+    shots: u32,
+    given_up: HashSet<Entity>,
+    holding_fire: bool,
+    path: Vec<Vec3>,
+    skip: HashSet<usize>,
+    goal: Option<usize>,
+    progress_timer: f32,
+    progress_position: Vec3,
+    stuck_count: u32
+}
+
+impl Bot {
+    fn new(profile: Profile, route: Vec<Vec3>) -> Self {
+        Self {
+            profile,
+            route,
+            seed: profile.seed,
+            clock: 0.0,
+            first_seen: HashMap::new(),
+            target: None,
+            aim_offset: Vec2::ZERO,
+            shots: 0,
+            given_up: HashSet::new(),
+            holding_fire: false,
+            path: Vec::new(),
+            skip: HashSet::new(),
+            goal: None,
+            progress_timer: 0.0,
+            progress_position: Vec3::ZERO,
+            stuck_count: 0
+        }
+    }
+
+    fn look(app: &mut App) -> Option<View> {
+        let player = app.world.resource::<PlayerEntity>().entity?;
+        let feet = app.world.get::<Transform>(player)?.translation - Vec3::Y * FEET;
+
+        let mut camera_query = app.world.query::<(&GlobalTransform, &CameraController)>();
+        let (camera_transform, camera) = camera_query.iter(&app.world).next()?;
+        let (eye, yaw, pitch) = (camera_transform.translation(), camera.yaw, camera.pitch);
+
+        // the start button is the only thing to shoot before the level starts
+        let targets = if *app.world.resource::<State<GameState>>().get() == GameState::Start {
+            let mut button_query = app.world.query_filtered::<(Entity, &GlobalTransform), With<StartButton>>();
+            button_query.iter(&app.world).map(|(entity, transform)| (entity, transform.translation())).collect()
+        } else {
+            let mut target_query = app.world.query_filtered::<(Entity, &GlobalTransform), With<TargetController>>();
+            target_query.iter(&app.world).map(|(entity, transform)| (entity, transform.translation())).collect()
+        };
+
+        Some(View { player, eye, feet, yaw, pitch, targets })
+    }
+
+    // Whether a shot from the eye would reach a point of the target, the same ray the gun uses
+    fn can_hit(app: &App, view: &View, target: Entity, point: Vec3) -> bool {
+        let offset = point - view.eye;
+        let filter = QueryFilter {
+            flags: QueryFilterFlags::EXCLUDE_SENSORS | QueryFilterFlags::ONLY_FIXED,
+            exclude_collider: Some(view.player),
+            ..Default::default()
+        };
+        app.world.resource::<RapierContext>()
+            .cast_ray(view.eye, offset.normalize(), offset.length() + 1.0, true, filter)
+            .is_some_and(|(entity, _)| entity == target)
+    }
+
+    // The middle of the part of a target that is not behind anything, a player aims at what they see
+    fn visible_point(app: &App, view: &View, target: Entity, position: Vec3) -> Option<Vec3> {
+        let direction = position - view.eye;
+        if direction.length() > VIEW_RANGE {
+            return None;
+        }
+        let right = direction.cross(Vec3::Y).normalize_or_zero();
+        let up = right.cross(direction).normalize_or_zero();
+
+        let mut samples = vec![position];
+        for step in 0..8 {
+            let angle = step as f32 / 8.0 * 2.0 * PI;
+            samples.push(position + (right * angle.cos() + up * angle.sin()) * TARGET_RADIUS * 0.7);
+        }
+        let visible: Vec<Vec3> = samples.into_iter().filter(|sample| Self::can_hit(app, view, target, *sample)).collect();
+        if visible.is_empty() {
+            return None;
+        }
+        let middle = visible.iter().sum::<Vec3>() / visible.len() as f32;
+        visible.into_iter().min_by(|a, b| a.distance(middle).total_cmp(&b.distance(middle)))
+    }
+
+    // Every miss on the same target lands the next flick closer
+    fn roll_aim_offset(&mut self) {
+        let angle = next_random(&mut self.seed) * 2.0 * PI;
+        let error = self.profile.aim_error * 0.5_f32.powi(self.shots as i32);
+        let distance = next_random(&mut self.seed).sqrt() * error;
+        self.aim_offset = Vec2::new(angle.cos(), angle.sin()) * distance;
+    }
+
+    // Turns towards a view no faster than the flick speed, returns whether it got there
+    fn turn_towards(&self, app: &mut App, view: &View, yaw: f32, pitch: f32) -> bool {
+        let difference = Vec2::new(wrap_angle(yaw - view.yaw), pitch - view.pitch);
+        let max_turn = self.profile.flick_speed * TICK;
+        let turn = if difference.length() > max_turn {
+            difference.normalize() * max_turn
+        } else {
+            difference
+        };
+        set_view(&mut app.world, view.yaw + turn.x, view.pitch + turn.y);
+        difference.length() < 0.0005
+    }
+
+    fn press<T: Copy + Eq + std::hash::Hash + Send + Sync + 'static>(app: &mut App, input: T, pressed: bool) {
+        let mut buttons = app.world.resource_mut::<ButtonInput<T>>();
+        if pressed {
+            buttons.press(input);
+        } else {
+            buttons.release(input);
+        }
+    }
+
+    // Decides the inputs for the next frame
+    fn step(&mut self, app: &mut App, map: &MapShape, grid: &NavGrid) -> Result<(), ()> {
+        self.clock += TICK;
+        Self::press(app, KeyCode::Space, false);
+
+        // every shot is a tap, the button is let go on the frame after
+        if self.holding_fire {
+            Self::press(app, MouseButton::Left, false);
+            self.holding_fire = false;
+            return Ok(());
+        }
+
+        let Some(view) = Self::look(app) else { return Ok(()) };
+
+        // remembers since when each target has been in sight, reacting starts from there
+        let visible: Vec<(Entity, Vec3)> = view.targets.iter()
+            .filter_map(|(entity, position)| Self::visible_point(app, &view, *entity, *position).map(|point| (*entity, point)))
+            .collect();
+        self.first_seen.retain(|entity, _| visible.iter().any(|(visible_entity, _)| visible_entity == entity));
+        for (entity, _) in &visible {
+            self.first_seen.entry(*entity).or_insert(self.clock);
+        }
+
+        // drops the target once it is dead or out of sight
+        if self.target.is_some_and(|target| !self.first_seen.contains_key(&target)) {
+            self.target = None;
+        }
+
+        // picks the visible target closest to the crosshair
+        if self.target.is_none() {
+            let forward = Quat::from_euler(EulerRot::YXZ, view.yaw, view.pitch, 0.0) * -Vec3::Z;
+            let closest = visible.iter()
+                .filter(|(entity, _)| !self.given_up.contains(entity))
+                .map(|(entity, point)| (*entity, forward.angle_between(*point - view.eye)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((entity, _)) = closest {
+                self.target = Some(entity);
+                self.shots = 0;
+                self.roll_aim_offset();
+            }
+        }
+
+        // keeps walking while aiming, there is no penalty for shooting on the move
+        let mut aiming = false;
+        if let Some(target) = self.target {
+            // a target that keeps being missed is probably only peeking out, it is left for a better spot
+            if self.shots >= GIVE_UP_SHOTS {
+                self.given_up.insert(target);
+                self.target = None;
+            } else if self.clock - self.first_seen[&target] >= self.profile.reaction_time {
+                aiming = true;
+                let point = visible.iter().find(|(entity, _)| *entity == target).map(|(_, point)| *point).unwrap();
+                if let Some((yaw, pitch)) = view_angles(view.eye, point) {
+                    if self.turn_towards(app, &view, yaw + self.aim_offset.x, pitch + self.aim_offset.y) {
+                        // the camera got there last frame, so the shot goes where the bot is looking
+                        Self::press(app, MouseButton::Left, true);
+                        self.holding_fire = true;
+                        self.shots += 1;
+                        self.roll_aim_offset();
+                    }
+                }
+            } else {
+                // still reacting, the view stays where it is
+                aiming = true;
+            }
+        }
+
+        self.walk(app, map, grid, &view, aiming)
+    }
+
+    // Holds the movement keys closest to a direction, whichever way the camera is facing
+    fn move_towards(app: &mut App, view: &View, direction: Vec3) {
+        let forward = Vec3::new(-view.yaw.sin(), 0.0, -view.yaw.cos());
+        let right = Vec3::new(view.yaw.cos(), 0.0, -view.yaw.sin());
+        let (ahead, sideways) = (direction.dot(forward), direction.dot(right));
+
+        Self::press(app, KeyCode::KeyW, ahead > 0.38);
+        Self::press(app, KeyCode::KeyS, ahead < -0.38);
+        Self::press(app, KeyCode::KeyD, sideways > 0.38);
+        Self::press(app, KeyCode::KeyA, sideways < -0.38);
+        // sprints whenever it can, the game holds it back once stamina runs out
+        Self::press(app, KeyCode::AltLeft, ahead > 0.38);
+    }
+
+    // Follows a path to somewhere a target can be seen from
+    fn walk(&mut self, app: &mut App, map: &MapShape, grid: &NavGrid, view: &View, aiming: bool) -> Result<(), ()> {
+        if self.path.is_empty() {
+            // stands still to shoot once it is where it was going
+            if aiming {
+                Self::move_towards(app, view, Vec3::ZERO);
+                return Ok(());
+            }
+
+            // got to the end without seeing anything, so that spot is no good
+            if let Some(goal) = self.goal.take() {
+                self.skip.insert(goal);
+            }
+
+            // heads for the targets in the order the level lists them, like a player who knows the route
+            let alive: Vec<Vec3> = view.targets.iter().map(|(_, position)| *position).collect();
+            let next = self.route.iter().find(|position| alive.iter().any(|alive| alive.distance(**position) < 0.01));
+            let positions: Vec<Vec3> = next.map_or(alive.clone(), |position| vec![*position]);
+            let path = grid.nearest(view.feet)
+                .and_then(|from| grid.path_to_view(map, from, &positions, TARGET_RADIUS, &self.skip))
+                .map(|(path, _)| path);
+            let Some(path) = path else {
+                Self::move_towards(app, view, Vec3::ZERO);
+                // nothing left to find while the level changes
+                return if view.targets.is_empty() { Ok(()) } else { Err(()) };
+            };
+            self.goal = grid.nearest(*path.last().unwrap());
+            self.given_up.clear();
+            self.path = path;
+            self.path.reverse();
+            self.progress_timer = 0.0;
+            self.progress_position = view.feet;
+        }
+
+        // waypoints count as reached once the player is over them
+        while let Some(waypoint) = self.path.last() {
+            if waypoint.xz().distance(view.feet.xz()) < CELL * 0.6 && (waypoint.y - view.feet.y).abs() < 0.6 {
+                self.path.pop();
+            } else {
+                break;
+            }
+        }
+        let Some(waypoint) = self.path.last().copied() else {
+            Self::move_towards(app, view, Vec3::ZERO);
+            return Ok(());
+        };
+
+        let direction = Vec3::new(waypoint.x - view.feet.x, 0.0, waypoint.z - view.feet.z).normalize_or_zero();
+        if !aiming {
+            if let Some((yaw, _)) = view_angles(view.feet, Vec3::new(waypoint.x, view.feet.y, waypoint.z)) {
+                self.turn_towards(app, view, yaw, 0.0);
+            }
+        }
+        Self::move_towards(app, view, direction);
+        // steps are jumped up
+        if waypoint.y - view.feet.y > 0.15 {
+            Self::press(app, KeyCode::Space, true);
+        }
+
+        // not getting anywhere, jumps and plans a new way
+        self.progress_timer += TICK;
+        if self.progress_timer > 1.0 {
+            if view.feet.distance(self.progress_position) < 0.3 {
+                self.stuck_count += 1;
+                if self.stuck_count > 5 {
+                    return Err(());
+                }
+                Self::press(app, KeyCode::Space, true);
+                if let Some(goal) = self.goal {
+                    self.skip.insert(goal);
+                }
+                self.goal = None;
+                self.path.clear();
+            } else {
+                self.stuck_count = 0;
+            }
+            self.progress_timer = 0.0;
+            self.progress_position = view.feet;
+        }
+
+        Ok(())
+    }
+}
+
+// The same collision the headless app put into the world
+fn map_shape(app: &App) -> MapShape {
+    MapShape::new(app.world.resource::<MapCollider>().collider.clone().expect("map collision did not load"))
+}
+
+fn time_limit(level: &LevelState) -> f32 {
+    let time_controller = TimeController::default();
+    match level {
+        LevelState::Level1 => time_controller.level_1_time,
+        LevelState::Level2 => time_controller.level_2_time,
+        LevelState::Level3 => time_controller.level_3_time,
+        LevelState::Level4 => time_controller.level_4_time,
+        LevelState::Level5 => time_controller.level_5_time,
+        _ => 0.0
+    }
+}
+
+fn level_positions(app: &App, level: &LevelState) -> Vec<Vec3> {
+    let levels = app.world.resource::<LevelController>();
+    match level {
+        LevelState::Level1 => levels.level_1_pos.clone(),
+        LevelState::Level2 => levels.level_2_pos.clone(),
+        LevelState::Level3 => levels.level_3_pos.clone(),
+        LevelState::Level4 => levels.level_4_pos.clone(),
+        LevelState::Level5 => levels.level_5_pos.clone(),
+        _ => Vec::new()
+    }
+}
+
+fn target_count(app: &mut App) -> usize {
+    app.world.query::<&TargetController>().iter(&app.world).count()
+}
+
+// Lets the bot play until the level is over, from the start button to the last target
+fn play_level(app: &mut App, map: &MapShape, grid: &NavGrid, profile: Profile) -> Outcome {
+    let level = level_state(app);
+    let max_frames = ((time_limit(&level) + 20.0) / TICK) as usize;
+    let mut bot = Bot::new(profile, level_positions(app, &level));
+    let mut time_used = 0.0;
+    let mut targets_left = target_count(app);
+
+    for _ in 0..max_frames {
+        if bot.step(app, map, grid).is_err() {
+            return Outcome::Stuck { targets_left };
+        }
+        app.update();
+
+        // the next level resets the timer on the frame it starts, so the time is from the frame before
+        let current_level = level_state(app);
+        if current_level == LevelState::Failed || (current_level != level && current_level == LevelState::Level1) {
+            return Outcome::TimedOut { targets_left };
+        }
+        if current_level != level {
+            return Outcome::Beaten { time: time_used + TICK };
+        }
+
+        if let Some(timer) = &app.world.resource::<TimeController>().time_left {
+            time_used = timer.elapsed_secs();
+        }
+        targets_left = target_count(app);
+    }
+
+    Outcome::TimedOut { targets_left }
+}
+
+// A fresh game at the start of a level, earlier levels are skipped by clearing them out
+fn app_at_level(level: &LevelState) -> App {
+    let mut app = headless_app();
+    start_game(&mut app);
+    while level_state(&app) != *level {
+        let mut target_query = app.world.query_filtered::<Entity, Or<(With<TargetController>, With<StartButton>)>>();
+        let targets: Vec<Entity> = target_query.iter(&app.world).collect();
+        for target in targets {
+            app.world.entity_mut(target).despawn_recursive();
+        }
+        app.world.resource_mut::<NextState<GameState>>().set(GameState::Playing);
+        for _ in 0..3 {
+            app.update();
+        }
+    }
+    assert_eq!(game_state(&app), GameState::Start);
+    app
+}
+
+#[test]
+fn bot_shoots_the_start_button_after_reacting() {
+    let mut app = headless_app();
+    start_game(&mut app);
+    let map = map_shape(&app);
+    // the start button is in sight from the spawn, nothing needs walking to
+    let grid = NavGrid::empty();
+
+    let mut bot = Bot::new(HUMAN, Vec::new());
+    let mut frames = 0;
+    while game_state(&app) == GameState::Start && frames < 120 {
+        bot.step(&mut app, &map, &grid).unwrap();
+        app.update();
+        frames += 1;
+    }
+
+    assert_eq!(game_state(&app), GameState::Playing);
+    assert!(frames as f32 * TICK >= HUMAN.reaction_time, "shot after {} frames, faster than reacting", frames);
+}
+
+#[test]
+fn every_target_can_be_seen_from_the_walkable_map() {
+    let app = headless_app();
+    let map = map_shape(&app);
+    let grid = NavGrid::build(&map, PlayerController::default().spawn_point);
+    let levels = LevelController::default();
+
+    let start = grid.nearest(PlayerController::default().spawn_point - Vec3::Y * FEET).expect("spawn is not on the grid");
+    let all_levels = [&levels.level_1_pos, &levels.level_2_pos, &levels.level_3_pos, &levels.level_4_pos, &levels.level_5_pos];
+    for (level, positions) in all_levels.iter().enumerate() {
+        for position in positions.iter() {
+            let path = grid.path_to_view(&map, start, &[*position], TARGET_RADIUS, &HashSet::new());
+            assert!(path.is_some(), "level {} target at {} can not be seen from anywhere walkable", level + 1, position);
+        }
+    }
+}
+
+// Balance report, run with `cargo test --test aim_bot -- --nocapture` to see the times
+#[test]
+fn human_profile_matches_the_known_level_results() {
+    let map = map_shape(&headless_app());
+    let grid = NavGrid::build(&map, PlayerController::default().spawn_point);
+    let mut changed = Vec::new();
+    let mut results = Vec::new();
+
+    for (level, known) in KNOWN_RESULTS {
+        let mut app = app_at_level(&level);
+        let limit = time_limit(&level);
+
+        let outcome = play_level(&mut app, &map, &grid, HUMAN);
+        results.push(match outcome {
+            Outcome::Beaten { time } => {
+                format!("{:?}: beaten in {:.1}s of {:.0}s ({:.0}%)", level, time, limit, time / limit * 100.0)
+            }
+            Outcome::TimedOut { targets_left } => {
+                format!("{:?}: ran out of time with {} targets left", level, targets_left)
+            }
+            Outcome::Stuck { targets_left } => {
+                format!("{:?}: got stuck with {} targets left", level, targets_left)
+            }
+        });
+
+        let same = match (known, &outcome) {
+            (Known::Beaten(known_time), Outcome::Beaten { time }) => (time - known_time).abs() <= TIME_TOLERANCE,
+            (Known::TimedOut(known_left), Outcome::TimedOut { targets_left }) => *targets_left == known_left,
+            _ => false
+        };
+        if !same {
+            changed.push(format!("{:?} was {:?}, now {:?}", level, known, outcome));
+        }
+    }
+
+    assert!(changed.is_empty(), "{} results changed: {:#?}\nall results: {:#?}", HUMAN.name, changed, results);
+}
@@ -0,0 +1,148 @@
+use bevy::{
+    audio::{
+        AudioSource,
+        Decodable,
+        Source
+    },
+    prelude::*
+};
+use game::{
+    LevelState,
+    audio::{
+        ogg_duration,
+        play_world_sounds,
+        select_ambience,
+        SHARED_AMBIENCE
+    },
+    structs::{
+        AudioController,
+        LevelController,
+        Settings,
+        WorldSound,
+        WorldSoundEvent
+    }
+};
+use std::{
+    fs,
+    time::Duration
+};
+
+// An ogg page holding one packet of up to 255 bytes
+fn ogg_page(flags: u8, granule_position: i64, sequence: u32, packet: &[u8]) -> Vec<u8> {
+    let mut page = b"OggS\0".to_vec();
+    page.push(flags);
+    page.extend_from_slice(&granule_position.to_le_bytes());
+    page.extend_from_slice(&7u32.to_le_bytes());
+    page.extend_from_slice(&sequence.to_le_bytes());
+    // the crc isn't checked
+    page.extend_from_slice(&[0; 4]);
+    page.push(1);
+    page.push(packet.len() as u8);
+    page.extend_from_slice(packet);
+    page
+}
+
+#[test]
+fn ogg_length_comes_from_the_headers() {
+    let bytes = fs::read("assets/ak-47.ogg").unwrap();
+
+    // decoding the whole file is what the headers save us from
+    let decoder = AudioSource { bytes: bytes.clone().into() }.decoder();
+    let sample_rate = decoder.sample_rate() as f32;
+    let samples_per_second = decoder.channels() as f32 * sample_rate;
+    let decoded = decoder.count() as f32 / samples_per_second;
+
+    // the decoder plays the last block out in full instead of stopping at the granule position,
+    // so it can run up to a block (2048 samples) longer
+    let duration = ogg_duration(&bytes).expect("the gunshot is an ogg vorbis file");
+    assert!(duration <= decoded && decoded - duration < 2048.0 / sample_rate, "{} from the headers, {} decoded", duration, decoded);
+
+    assert_eq!(ogg_duration(b"not an ogg file"), None);
+    assert_eq!(ogg_duration(&bytes[..20]), None);
+}
+
+#[test]
+fn ogg_length_ignores_capture_patterns_in_the_audio() {
+    // identification packet: type, "vorbis", version, one channel and 44100 hz
+    let mut identification = b"\x01vorbis".to_vec();
+    identification.extend_from_slice(&0u32.to_le_bytes());
+    identification.push(1);
+    identification.extend_from_slice(&44100u32.to_le_bytes());
+    // audio that happens to hold "OggS" followed by what would read as a huge granule position
+    let mut audio = vec![0x55; 40];
+    audio.extend_from_slice(b"OggS\0\x04\xff\xff\xff\xff\xff\xff\xff\x00");
+    audio.extend_from_slice(&[0x55; 40]);
+
+    let mut bytes = ogg_page(0x02, 0, 0, &identification);
+    bytes.extend(ogg_page(0x00, 44100, 1, &audio));
+    bytes.extend(ogg_page(0x04, 88200, 2, &audio));
+    assert_eq!(ogg_duration(&bytes), Some(2.0));
+
+    // a stream cut off before it ends has no length
+    let cut = bytes.len() - 10;
+    assert_eq!(ogg_duration(&bytes[..cut]), None);
+    let unfinished = [ogg_page(0x02, 0, 0, &identification), ogg_page(0x00, 44100, 1, &audio)].concat();
+    assert_eq!(ogg_duration(&unfinished), None);
+}
+
+#[test]
+fn levels_play_their_own_ambience() {
+    let mut app = App::new();
+    app
+    .add_plugins((MinimalPlugins, AssetPlugin::default()))
+    .init_asset::<AudioSource>()
+    .init_state::<LevelState>()
+    .init_resource::<AudioController>()
+    .insert_resource(LevelController {
+        ambience_tracks: [None, Some("level2_ambience.ogg"), None, None, None],
+        ..default()
+    })
+    .add_systems(OnEnter(LevelState::Level1), select_ambience)
+    .add_systems(OnEnter(LevelState::Level2), select_ambience);
+
+    let track = |app: &App| {
+        let handle = app.world.resource::<AudioController>().ambience_handle.clone();
+        handle.and_then(|handle| handle.path().map(|path| path.to_string()))
+    };
+
+    // level 1 names no track of its own
+    app.update();
+    assert_eq!(track(&app).as_deref(), Some(SHARED_AMBIENCE));
+
+    app.world.resource_mut::<NextState<LevelState>>().set(LevelState::Level2);
+    app.update();
+    assert_eq!(track(&app).as_deref(), Some("level2_ambience.ogg"));
+}
+
+#[test]
+fn world_sounds_play_where_they_happened() {
+    let mut app = App::new();
+    app
+    .add_plugins((MinimalPlugins, AssetPlugin::default()))
+    .init_asset::<AudioSource>()
+    .init_asset::<Pitch>()
+    .init_resource::<Settings>()
+    .add_event::<WorldSoundEvent>()
+    .add_systems(Update, play_world_sounds);
+    let footstep = app.world.resource_mut::<Assets<Pitch>>().add(Pitch::new(110.0, Duration::from_millis(50)));
+    app.world.insert_resource(AudioController {
+        gunshot_handle: Some(Handle::default()),
+        footstep_handle: Some(footstep),
+        ..default()
+    });
+
+    let gunshot_position = Vec3::new(4.0, 1.0, -2.0);
+    let footstep_position = Vec3::new(-3.0, 0.0, 5.0);
+    app.world.send_event(WorldSoundEvent { position: gunshot_position, sound: WorldSound::Gunshot });
+    app.world.send_event(WorldSoundEvent { position: footstep_position, sound: WorldSound::Footstep });
+    app.update();
+
+    let mut gunshot_query = app.world.query::<(&PlaybackSettings, &Transform, &Handle<AudioSource>)>();
+    let (settings, transform, _) = gunshot_query.single(&app.world);
+    assert!(settings.spatial);
+    assert_eq!(transform.translation, gunshot_position);
+    let mut footstep_query = app.world.query::<(&PlaybackSettings, &Transform, &Handle<Pitch>)>();
+    let (settings, transform, _) = footstep_query.single(&app.world);
+    assert!(settings.spatial);
+    assert_eq!(transform.translation, footstep_position);
+}
@@ -0,0 +1,42 @@
+// Helpers shared by the headless tests, not every test file uses all of them
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+use game::{
+    GameState,
+    LevelState,
+    structs::MenuButtonAction
+};
+
+pub fn run(app: &mut App, updates: usize) {
+    for _ in 0..updates {
+        app.update();
+    }
+}
+
+pub fn game_state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().get()
+}
+
+pub fn level_state(app: &App) -> LevelState {
+    app.world.resource::<State<LevelState>>().get().clone()
+}
+
+// Clicks the first menu button doing an action
+pub fn press_button(app: &mut App, is_action: fn(&MenuButtonAction) -> bool) {
+    let mut button_query = app.world.query::<(&MenuButtonAction, &mut Interaction)>();
+    let (_, mut interaction) = button_query.iter_mut(&mut app.world)
+        .find(|(action, _)| is_action(action))
+        .expect("no such button");
+    *interaction = Interaction::Pressed;
+    run(app, 2);
+}
+
+// Goes from the main menu into the start room of level 1
+pub fn start_game(app: &mut App) {
+    app.update();
+    assert_eq!(game_state(app), GameState::MainMenu);
+    press_button(app, |action| matches!(action, MenuButtonAction::Play));
+    run(app, 30);
+    assert_eq!(game_state(app), GameState::Start);
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use game::{
+    crosshair::update_scope_overlay,
+    structs::{
+        Crosshair,
+        GunController,
+        Scope,
+        ScopeOverlay
+    }
+};
+
+// The gun, the scope overlay and the crosshair, with the overlay hidden like it is spawned
+fn scope_app(scope: Option<Scope>) -> (App, Entity, Entity, Entity) {
+    let mut app = App::new();
+    app.add_systems(Update, update_scope_overlay);
+
+    let gun = app.world.spawn((GunController { scope, ..Default::default() }, Visibility::Inherited)).id();
+    let overlay = app.world.spawn((ScopeOverlay, Visibility::Hidden)).id();
+    let crosshair = app.world.spawn((Crosshair::default(), Visibility::Inherited)).id();
+    (app, gun, overlay, crosshair)
+}
+
+fn aim(app: &mut App, gun: Entity, aim_amount: f32) {
+    app.world.get_mut::<GunController>(gun).unwrap().aim_amount = aim_amount;
+    app.update();
+}
+
+fn visibility(app: &App, entity: Entity) -> Visibility {
+    *app.world.get::<Visibility>(entity).unwrap()
+}
+
+#[test]
+fn scope_overlay_replaces_the_crosshair_once_fully_scoped() {
+    let (mut app, gun, overlay, crosshair) = scope_app(Some(Scope::default()));
+
+    aim(&mut app, gun, 0.5);
+    assert_eq!(visibility(&app, overlay), Visibility::Hidden);
+    assert_eq!(visibility(&app, crosshair), Visibility::Inherited);
+
+    aim(&mut app, gun, 1.0);
+    assert_eq!(visibility(&app, overlay), Visibility::Inherited);
+    assert_eq!(visibility(&app, crosshair), Visibility::Hidden);
+    // the view model would be in the way of the scope
+    assert_eq!(visibility(&app, gun), Visibility::Hidden);
+
+    aim(&mut app, gun, 0.9);
+    assert_eq!(visibility(&app, overlay), Visibility::Hidden);
+    assert_eq!(visibility(&app, crosshair), Visibility::Inherited);
+    assert_eq!(visibility(&app, gun), Visibility::Inherited);
+}
+
+#[test]
+fn iron_sights_keep_the_crosshair() {
+    let (mut app, gun, overlay, crosshair) = scope_app(None);
+
+    aim(&mut app, gun, 1.0);
+    assert_eq!(visibility(&app, overlay), Visibility::Hidden);
+    assert_eq!(visibility(&app, crosshair), Visibility::Inherited);
+    assert_eq!(visibility(&app, gun), Visibility::Inherited);
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use bevy::{
+    input::gamepad::{
+        GamepadAxisChangedEvent,
+        GamepadButtonChangedEvent,
+        GamepadConnection,
+        GamepadConnectionEvent,
+        GamepadEvent,
+        GamepadInfo
+    },
+    prelude::*
+};
+use game::{
+    GameState,
+    MenuState,
+    headless::headless_app,
+    structs::{
+        CameraController,
+        MenuButtonAction,
+        MenuFocus,
+        PlayerController,
+        Settings
+    }
+};
+use common::run;
+
+const GAMEPAD: Gamepad = Gamepad { id: 0 };
+
+// Plugs in a gamepad the way gilrs reports it
+fn connect_gamepad(app: &mut App) {
+    app.world.send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+        GAMEPAD,
+        GamepadConnection::Connected(GamepadInfo { name: "test pad".to_string() })
+    )));
+}
+
+fn move_axis(app: &mut App, axis_type: GamepadAxisType, value: f32) {
+    app.world.send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(GAMEPAD, axis_type, value)));
+}
+
+fn set_button(app: &mut App, button_type: GamepadButtonType, value: f32) {
+    app.world.send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(GAMEPAD, button_type, value)));
+}
+
+// Presses and releases a button over two updates
+fn tap_button(app: &mut App, button_type: GamepadButtonType) {
+    set_button(app, button_type, 1.0);
+    app.update();
+    set_button(app, button_type, 0.0);
+    app.update();
+}
+
+fn start_room(app: &mut App) {
+    app.update();
+    connect_gamepad(app);
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Start);
+    run(app, 60);
+}
+
+fn player_position(app: &mut App) -> Vec3 {
+    let mut player_query = app.world.query_filtered::<&Transform, With<PlayerController>>();
+    player_query.single(&app.world).translation
+}
+
+#[test]
+fn left_stick_moves_the_player() {
+    let mut app = headless_app();
+    start_room(&mut app);
+    // the player only walks once the level is playing
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Playing);
+    run(&mut app, 2);
+    let start = player_position(&mut app);
+    let mut player_query = app.world.query_filtered::<&Transform, With<PlayerController>>();
+    let forward = player_query.single(&app.world).forward();
+
+    move_axis(&mut app, GamepadAxisType::LeftStickY, 1.0);
+    run(&mut app, 30);
+
+    let moved = player_position(&mut app) - start;
+    assert!(moved.dot(*forward) > 0.5, "player moved {} instead of forward", moved);
+
+    // letting go of the stick stops the player
+    move_axis(&mut app, GamepadAxisType::LeftStickY, 0.0);
+    run(&mut app, 30);
+    let stopped = player_position(&mut app);
+    run(&mut app, 10);
+    assert!(player_position(&mut app).distance(stopped) < 0.01);
+}
+
+#[test]
+fn right_stick_turns_the_camera() {
+    let mut app = headless_app();
+    start_room(&mut app);
+    let mut camera_query = app.world.query::<&CameraController>();
+    let (yaw, pitch) = {
+        let camera = camera_query.single(&app.world);
+        (camera.yaw, camera.pitch)
+    };
+
+    move_axis(&mut app, GamepadAxisType::RightStickX, 1.0);
+    move_axis(&mut app, GamepadAxisType::RightStickY, 0.5);
+    run(&mut app, 10);
+
+    let camera = camera_query.single(&app.world);
+    // right turns the yaw down, up raises the pitch
+    assert!(camera.yaw < yaw, "yaw went from {} to {}", yaw, camera.yaw);
+    assert!(camera.pitch > pitch, "pitch went from {} to {}", pitch, camera.pitch);
+}
+
+// How far the camera turns from holding the right stick
+fn stick_turn(app: &mut App) -> Vec2 {
+    let mut camera_query = app.world.query::<&CameraController>();
+    let (yaw, pitch) = {
+        let camera = camera_query.single(&app.world);
+        (camera.yaw, camera.pitch)
+    };
+    move_axis(app, GamepadAxisType::RightStickX, 1.0);
+    move_axis(app, GamepadAxisType::RightStickY, 0.5);
+    run(app, 10);
+    let camera = camera_query.single(&app.world);
+    Vec2::new(yaw - camera.yaw, camera.pitch - pitch)
+}
+
+#[test]
+fn stick_look_follows_the_axis_sensitivity() {
+    let mut app = headless_app();
+    start_room(&mut app);
+    let default_turn = stick_turn(&mut app);
+
+    let mut app = headless_app();
+    start_room(&mut app);
+    let mut settings = app.world.resource_mut::<Settings>();
+    settings.sensitivity_x = 2.0;
+    settings.sensitivity_y = 0.5;
+    let scaled_turn = stick_turn(&mut app);
+
+    assert!((scaled_turn.x / default_turn.x - 2.0).abs() < 0.01, "yaw turned {} instead of {}", scaled_turn.x, default_turn.x * 2.0);
+    assert!((scaled_turn.y / default_turn.y - 0.5).abs() < 0.01, "pitch turned {} instead of {}", scaled_turn.y, default_turn.y * 0.5);
+}
+
+#[test]
+fn dpad_and_south_press_menu_buttons() {
+    let mut app = headless_app();
+    app.update();
+    connect_gamepad(&mut app);
+    app.update();
+
+    let mut button_query = app.world.query_filtered::<(Entity, &MenuButtonAction, &BackgroundColor), With<Button>>();
+    let colors: Vec<(Entity, Color)> = button_query.iter(&app.world).map(|(entity, _, color)| (entity, color.0)).collect();
+
+    // the first press only shows the focus, the next ones move it until the settings button
+    let mut presses = 0;
+    loop {
+        tap_button(&mut app, GamepadButtonType::DPadDown);
+        let (focused, _) = app.world.resource::<MenuFocus>().highlighted.expect("a button should be focused");
+        if matches!(button_query.get(&app.world, focused).unwrap().1, MenuButtonAction::Settings) {
+            break;
+        }
+        presses += 1;
+        assert!(presses <= colors.len(), "the focus never reached the settings button");
+    }
+
+    // only the focused button is highlighted, the rest keep their colour
+    let (focused, _) = app.world.resource::<MenuFocus>().highlighted.unwrap();
+    for (entity, color) in &colors {
+        let current = button_query.get(&app.world, *entity).unwrap().2.0;
+        if *entity == focused {
+            assert_ne!(current, *color);
+        } else {
+            assert_eq!(current, *color);
+        }
+    }
+
+    tap_button(&mut app, GamepadButtonType::South);
+    app.update();
+    assert_eq!(app.world.resource::<State<MenuState>>().get(), &MenuState::Settings);
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use bevy::prelude::*;
+use game::{
+    GameState,
+    headless::{
+        headless_app,
+        look_at
+    },
+    structs::{
+        MapCollider,
+        PlayerController,
+        StartButton
+    }
+};
+use common::run;
+
+#[test]
+fn loads_the_map_collision() {
+    let app = headless_app();
+    assert!(app.world.resource::<MapCollider>().collider.is_some());
+}
+
+#[test]
+fn player_lands_on_the_map() {
+    let mut app = headless_app();
+    app.update();
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Start);
+    run(&mut app, 120);
+
+    let mut player_query = app.world.query_filtered::<&Transform, With<PlayerController>>();
+    let position = player_query.single(&app.world).translation;
+    let spawn_point = PlayerController::default().spawn_point;
+    assert!((position.y - spawn_point.y).abs() < 1.0, "player fell to {}", position);
+}
+
+#[test]
+fn shooting_the_start_button_starts_playing() {
+    let mut app = headless_app();
+    app.update();
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Start);
+    run(&mut app, 60);
+    assert_eq!(app.world.resource::<State<GameState>>().get(), &GameState::Start);
+
+    let mut button_query = app.world.query_filtered::<&GlobalTransform, With<StartButton>>();
+    let button = button_query.single(&app.world).translation();
+    look_at(&mut app.world, button);
+    app.update();
+
+    app.world.resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Left);
+    run(&mut app, 2);
+    assert_eq!(app.world.resource::<State<GameState>>().get(), &GameState::Playing);
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use game::{
+    heatmap::{
+        blend,
+        encode_png,
+        heat,
+        render_heights,
+        shade_layout,
+        HeatmapLayer,
+        TopDownView
+    },
+    structs::{
+        HitKind,
+        RunTelemetry,
+        ShotRecord
+    },
+    telemetry::to_json
+};
+
+fn shot(hit: HitKind, distance: Option<f32>) -> ShotRecord {
+    ShotRecord {
+        time: 1.0,
+        origin: Vec3::new(1.0, 0.5, 2.0),
+        direction: Vec3::X,
+        hit,
+        hit_entity: None,
+        target_id: None,
+        distance,
+        velocity: Vec3::ZERO
+    }
+}
+
+// A 10m square floor at height 0 with a 1m high box standing in one corner
+fn floor_and_box() -> (Vec<Vec3>, Vec<[u32; 3]>) {
+    let vertices = vec![
+        Vec3::new(0.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 10.0),
+        Vec3::new(0.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 2.0), Vec3::new(0.0, 1.0, 2.0)
+    ];
+    (vertices, vec![[0, 1, 2], [0, 2, 3], [4, 6, 5], [4, 7, 6]])
+}
+
+#[test]
+fn layout_is_drawn_from_above() {
+    let (vertices, triangles) = floor_and_box();
+    let view = TopDownView::fit(&vertices, 100);
+    let heights = render_heights(&view, &vertices, &triangles);
+
+    let height_at = |x: f32, z: f32| {
+        let pixel = view.to_pixel(Vec3::new(x, 0.0, z));
+        heights[(pixel.y as u32 * view.size + pixel.x as u32) as usize]
+    };
+    assert_eq!(height_at(1.0, 1.0), 1.0);
+    assert_eq!(height_at(6.0, 6.0), 0.0);
+    assert_eq!(height_at(-1.0, 5.0), f32::NEG_INFINITY);
+
+    // the top of the box is lighter than the floor
+    let layout = shade_layout(&view, &heights);
+    let colour_at = |x: f32, z: f32| {
+        let pixel = view.to_pixel(Vec3::new(x, 0.0, z));
+        layout[(pixel.y as u32 * view.size + pixel.x as u32) as usize]
+    };
+    assert!(colour_at(1.0, 1.0)[0] > colour_at(6.0, 6.0)[0]);
+}
+
+#[test]
+fn heat_is_hottest_where_points_gather() {
+    let (vertices, _) = floor_and_box();
+    let view = TopDownView::fit(&vertices, 100);
+    let busy = Vec3::new(3.0, 0.0, 3.0);
+    let quiet = Vec3::new(8.0, 0.0, 8.0);
+    let values = heat(&view, &[busy, busy, busy, quiet]);
+
+    let value_at = |point: Vec3| {
+        let pixel = view.to_pixel(point);
+        values[(pixel.y as u32 * view.size + pixel.x as u32) as usize]
+    };
+    assert!(value_at(busy) > 2.5 * value_at(quiet));
+    assert!(value_at(quiet) > 0.0);
+    assert_eq!(value_at(Vec3::new(5.5, 0.0, 5.5)), 0.0);
+
+    // untouched pixels keep the layout colour, the hottest one turns red
+    let layout = vec![[100, 100, 100]; values.len()];
+    let image = blend(&layout, &values);
+    let pixel = view.to_pixel(busy);
+    let index = (pixel.y as u32 * view.size + pixel.x as u32) as usize * 3;
+    assert!(image[index] > 200 && image[index + 2] < 50);
+    assert_eq!(&image[..3], &[100, 100, 100]);
+
+    let png = encode_png(view.size, &image).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+}
+
+#[test]
+fn layers_pick_their_points_from_the_log() {
+    let run = RunTelemetry {
+        level: 2,
+        cleared: false,
+        time: 4.0,
+        shots: vec![shot(HitKind::StartButton, Some(1.0)), shot(HitKind::Map, Some(3.0)), shot(HitKind::Target, Some(2.0)), shot(HitKind::Nothing, None)],
+        positions: vec![Vec3::ZERO, Vec3::ONE]
+    };
+    // the log reads back the same way the heatmap tool loads it
+    let run: RunTelemetry = serde_json::from_str(&to_json(&run).unwrap()).unwrap();
+
+    assert_eq!(HeatmapLayer::Positions.points(&run), vec![Vec3::ZERO, Vec3::ONE]);
+    assert_eq!(HeatmapLayer::Shots.points(&run).len(), 3);
+    assert_eq!(HeatmapLayer::Misses.points(&run), vec![Vec3::new(4.0, 0.5, 2.0)]);
+    assert_eq!(HeatmapLayer::Deaths.points(&run), vec![Vec3::ONE]);
+    let cleared = RunTelemetry { cleared: true, ..run };
+    assert!(HeatmapLayer::Deaths.points(&cleared).is_empty());
+}
@@ -0,0 +1,89 @@
+use bevy::{
+    input::{
+        keyboard::{
+            Key,
+            KeyboardInput
+        },
+        ButtonState
+    },
+    prelude::*
+};
+use game::{
+    MenuState,
+    headless::headless_app,
+    input::binding_name,
+    structs::{
+        InputAction,
+        InputBinding,
+        InputBindings,
+        KeyNames,
+        RebindState
+    }
+};
+
+// Sends a key press the way the window does, with the key the layout puts there
+fn press_key(app: &mut App, key_code: KeyCode, logical_key: Key) {
+    app.world.send_event(KeyboardInput {
+        key_code,
+        logical_key,
+        state: ButtonState::Pressed,
+        window: Entity::PLACEHOLDER
+    });
+}
+
+#[test]
+fn rebinding_a_used_key_swaps_the_bindings() {
+    let mut bindings = InputBindings::default();
+    bindings.rebind(InputAction::Jump, InputBinding::Key(KeyCode::KeyW)).unwrap();
+
+    assert!(bindings.get(InputAction::Jump).contains(&InputBinding::Key(KeyCode::KeyW)));
+    assert!(!bindings.get(InputAction::Jump).contains(&InputBinding::Key(KeyCode::Space)));
+    // forward takes over the old jump key instead of being left unbound
+    assert!(bindings.get(InputAction::MoveForward).contains(&InputBinding::Key(KeyCode::Space)));
+    assert!(!bindings.get(InputAction::MoveForward).contains(&InputBinding::Key(KeyCode::KeyW)));
+}
+
+#[test]
+fn rebinding_never_leaves_an_action_unbound() {
+    let mut bindings = InputBindings::default();
+    bindings.actions.insert(InputAction::Walk, Vec::new());
+
+    // walk has nothing to give fire in exchange
+    assert!(bindings.rebind(InputAction::Walk, InputBinding::Mouse(MouseButton::Left)).is_err());
+    assert_eq!(bindings.get(InputAction::Fire), InputBindings::default().get(InputAction::Fire));
+    assert!(bindings.get(InputAction::Walk).is_empty());
+}
+
+#[test]
+fn escape_can_be_bound() {
+    let mut app = headless_app();
+    app.update();
+    app.world.resource_mut::<NextState<MenuState>>().set(MenuState::Controls);
+    app.update();
+    app.world.insert_resource(RebindState {
+        action: Some(InputAction::Reload),
+        armed: true
+    });
+
+    press_key(&mut app, KeyCode::Escape, Key::Escape);
+    app.update();
+
+    let bindings = app.world.resource::<InputBindings>();
+    assert!(bindings.get(InputAction::Reload).contains(&InputBinding::Key(KeyCode::Escape)));
+    // pause gets the old reload key
+    assert!(bindings.get(InputAction::Pause).contains(&InputBinding::Key(KeyCode::KeyR)));
+    assert_eq!(app.world.resource::<RebindState>().action, None);
+}
+
+#[test]
+fn keys_are_named_after_the_layout() {
+    let mut app = headless_app();
+    app.update();
+    let w_key = InputBinding::Key(KeyCode::KeyW);
+    assert_eq!(binding_name(&w_key, app.world.resource::<KeyNames>()), "W");
+
+    // azerty puts z where qwerty has w
+    press_key(&mut app, KeyCode::KeyW, Key::Character("z".into()));
+    app.update();
+    assert_eq!(binding_name(&w_key, app.world.resource::<KeyNames>()), "Z");
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*,
+    time::TimeUpdateStrategy
+};
+use game::{
+    GameState,
+    LevelState,
+    headless::{
+        headless_app,
+        view_angles,
+        TICK
+    },
+    replay::{
+        decode_replay,
+        encode_replay
+    },
+    structs::{
+        CameraController,
+        HitKind,
+        PlayerController,
+        Replay,
+        ReplayPlayback,
+        ReplayRecorder,
+        Settings,
+        StartButton,
+        TargetController
+    }
+};
+use std::time::Duration;
+use common::{
+    game_state,
+    run
+};
+
+// Turns towards a point with mouse motion, so the turn ends up in the replay
+fn mouse_towards(app: &mut App, point: Vec3) {
+    let mut camera_query = app.world.query::<(&CameraController, &GlobalTransform)>();
+    let (camera, camera_transform) = camera_query.single(&app.world);
+    let (yaw, pitch) = view_angles(camera_transform.translation(), point).unwrap();
+
+    let settings = app.world.resource::<Settings>();
+    let sensitivity = camera.sensitivity * settings.sensitivity * camera.zoom_sensitivity;
+    let delta = Vec2::new(
+        -(yaw - camera.yaw) / (sensitivity * settings.sensitivity_x),
+        -(pitch - camera.pitch) / (sensitivity * settings.sensitivity_y)
+    );
+    app.world.send_event(MouseMotion { delta });
+}
+
+// Where the player is, where it looks and which targets are still up
+fn snapshot(app: &mut App) -> (Vec3, f32, f32, usize) {
+    let mut player_query = app.world.query_filtered::<&Transform, With<PlayerController>>();
+    let translation = player_query.single(&app.world).translation;
+    let mut camera_query = app.world.query::<&CameraController>();
+    let camera = camera_query.single(&app.world);
+    let (yaw, pitch) = (camera.yaw, camera.pitch);
+    let mut target_query = app.world.query::<&TargetController>();
+    let targets = target_query.iter(&app.world).count();
+    (translation, yaw, pitch, targets)
+}
+
+// Shoots the start button and runs around turning and shooting for a few seconds
+fn record_run() -> App {
+    let mut app = headless_app();
+    app.update();
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Start);
+    run(&mut app, 30);
+
+    let mut button_query = app.world.query_filtered::<&GlobalTransform, With<StartButton>>();
+    let button = button_query.single(&app.world).translation();
+    mouse_towards(&mut app, button);
+    app.update();
+    app.world.resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Left);
+    run(&mut app, 2);
+    app.world.resource_mut::<ButtonInput<MouseButton>>().release(MouseButton::Left);
+    assert_eq!(game_state(&app), GameState::Playing);
+
+    for tick in 0..300 {
+        let mut keys = app.world.resource_mut::<ButtonInput<KeyCode>>();
+        for (key, held) in [
+            (KeyCode::KeyW, tick < 150),
+            (KeyCode::KeyD, (60..200).contains(&tick)),
+            (KeyCode::AltLeft, (90..140).contains(&tick)),
+            (KeyCode::Space, tick == 100)
+        ] {
+            if held {
+                keys.press(key);
+            } else {
+                keys.release(key);
+            }
+        }
+
+        let mut mouse = app.world.resource_mut::<ButtonInput<MouseButton>>();
+        if tick % 30 < 8 {
+            mouse.press(MouseButton::Left);
+        } else {
+            mouse.release(MouseButton::Left);
+        }
+        app.world.send_event(MouseMotion { delta: Vec2::new(4.0, (tick as f32 * 0.1).sin() * 3.0) });
+        app.update();
+    }
+
+    app
+}
+
+fn recorded(app: &App) -> Replay {
+    app.world.resource::<ReplayRecorder>().current.clone().expect("the level should be recording")
+}
+
+fn assert_same_replay(first: &Replay, second: &Replay) {
+    assert_eq!(first.level, second.level);
+    assert_eq!(first.cleared, second.cleared);
+    assert_eq!(first.ticks, second.ticks);
+    assert_eq!(first.start, second.start);
+    assert_eq!(first.frames, second.frames);
+    assert_eq!(first.poses, second.poses);
+}
+
+#[test]
+fn replay_files_read_back_the_same() {
+    let app = record_run();
+    let replay = recorded(&app);
+    assert!(replay.ticks >= 300);
+    // a pose for the ghost is kept for every tick, with the shots and what they hit in them
+    assert_eq!(replay.poses.len(), replay.frames.len());
+    assert!(replay.poses.iter().any(|pose| pose.shot.is_some_and(|shot| shot.hit == HitKind::StartButton)));
+
+    let bytes = encode_replay(&replay).unwrap();
+    assert_same_replay(&replay, &decode_replay(&bytes).unwrap());
+
+    assert!(decode_replay(&bytes[..bytes.len() - 1]).is_err());
+    assert!(decode_replay(b"not a replay").is_err());
+    // the version follows the magic, any other version is refused
+    let mut other_version = bytes.clone();
+    other_version[4] += 1;
+    assert!(decode_replay(&other_version).is_err());
+}
+
+// Plays a replay back until its last frame, simulating a number of fixed ticks every update
+fn play_back(replay: Replay, ticks_per_update: u32) -> App {
+    let frame_count = replay.frames.len();
+    let mut playback = headless_app();
+    playback.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(TICK) * ticks_per_update));
+    playback.insert_resource(ReplayPlayback::new(replay));
+    let mut updates = 0;
+    while playback.world.resource::<ReplayPlayback>().index < frame_count && updates < frame_count + 100 {
+        playback.update();
+        updates += 1;
+    }
+    playback
+}
+
+#[test]
+fn playing_a_replay_reproduces_the_run() {
+    let mut recording = record_run();
+    let replay = recorded(&recording);
+    let expected = snapshot(&mut recording);
+
+    let mut playback = play_back(replay, 1);
+    let (translation, yaw, pitch, targets) = snapshot(&mut playback);
+    assert!(translation.distance(expected.0) < 0.001, "ended at {} instead of {}", translation, expected.0);
+    assert_eq!((yaw, pitch, targets), (expected.1, expected.2, expected.3));
+    assert_same_replay(&recorded(&playback), &recorded(&recording));
+}
+
+#[test]
+fn replays_play_out_the_same_at_any_frame_rate() {
+    let recording = record_run();
+    let replay = recorded(&recording);
+
+    // two ticks a frame, the replay still moves one frame per tick
+    let mut playback = play_back(replay.clone(), 2);
+    let mut recorded_again = recorded(&playback);
+    // the last update can run one tick past the end of the replay
+    recorded_again.frames.truncate(replay.frames.len());
+    recorded_again.poses.truncate(replay.poses.len());
+    recorded_again.ticks = replay.ticks;
+    assert_same_replay(&recorded_again, &replay);
+    assert_eq!(snapshot(&mut playback).3, snapshot(&mut play_back(replay, 1)).3);
+}
+
+#[test]
+fn clearing_a_level_finishes_the_replay() {
+    let mut app = record_run();
+    let mut target_query = app.world.query_filtered::<Entity, With<TargetController>>();
+    let targets: Vec<Entity> = target_query.iter(&app.world).collect();
+    for target in targets {
+        app.world.entity_mut(target).despawn_recursive();
+    }
+    run(&mut app, 3);
+
+    assert_eq!(app.world.resource::<State<LevelState>>().get(), &LevelState::Level2);
+    let recorder = app.world.resource::<ReplayRecorder>();
+    let replay = recorder.last.as_ref().expect("the level 1 replay should be finished");
+    assert_eq!(replay.level, 1);
+    assert!(replay.cleared);
+    // the first clear is the personal best the ghost follows
+    assert_eq!(recorder.best.get(&1).map(|best| best.ticks), Some(replay.ticks));
+}
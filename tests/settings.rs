@@ -0,0 +1,80 @@
+use bevy::{
+    app::AppExit,
+    prelude::*
+};
+use game::{
+    MenuState,
+    headless::headless_app,
+    settings::{
+        load_settings,
+        vertical_fov
+    },
+    structs::{
+        ConfigDirectory,
+        Settings
+    }
+};
+use std::{
+    env,
+    fs,
+    path::PathBuf
+};
+
+// A config directory of its own for each test, removed before it is used
+fn config_directory(name: &str) -> ConfigDirectory {
+    let directory: PathBuf = env::temp_dir().join(format!("game-settings-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    ConfigDirectory(Some(directory))
+}
+
+fn saved_volume(config: &ConfigDirectory) -> f32 {
+    load_settings(config).ambience_volume
+}
+
+#[test]
+fn closing_a_settings_sub_menu_saves() {
+    let config = config_directory("sub-menu");
+    let mut app = headless_app();
+    app.world.insert_resource(ConfigDirectory(config.0.clone()));
+    app.update();
+
+    app.world.resource_mut::<NextState<MenuState>>().set(MenuState::Audio);
+    app.update();
+    app.world.resource_mut::<Settings>().ambience_volume = 0.7;
+    app.update();
+    app.world.resource_mut::<NextState<MenuState>>().set(MenuState::Settings);
+    app.update();
+
+    assert_eq!(saved_volume(&config), 0.7);
+    let _ = fs::remove_dir_all(config.0.unwrap());
+}
+
+#[test]
+fn quitting_saves() {
+    let config = config_directory("quit");
+    let mut app = headless_app();
+    app.world.insert_resource(ConfigDirectory(config.0.clone()));
+    app.update();
+
+    app.world.resource_mut::<Settings>().ambience_volume = 0.2;
+    app.world.send_event(AppExit);
+    app.update();
+
+    assert_eq!(saved_volume(&config), 0.2);
+    let _ = fs::remove_dir_all(config.0.unwrap());
+}
+
+#[test]
+fn saved_field_of_view_is_horizontal_at_4_3() {
+    let config = config_directory("fov");
+    let directory = config.0.clone().unwrap();
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("settings.ron"), "(fov: 90.0, ambience_volume: 0.5)").unwrap();
+
+    let settings = load_settings(&config);
+    assert_eq!(settings.fov, 90.0);
+    // 90 degrees across a 4:3 screen is about 73.74 degrees up and down
+    assert!((vertical_fov(settings.fov).to_degrees() - 73.74).abs() < 0.01, "vertical fov is {}", vertical_fov(settings.fov).to_degrees());
+    assert_eq!(settings.ambience_volume, 0.5);
+    let _ = fs::remove_dir_all(directory);
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*
+};
+use game::{
+    GameState,
+    LevelState,
+    headless::{
+        headless_app,
+        look_at,
+        TICK
+    },
+    structs::{
+        CameraController,
+        GameEntity,
+        LastState,
+        MenuButtonAction,
+        StartButton,
+        TargetController,
+        TargetHitEvent,
+        TimeController
+    }
+};
+use common::{
+    game_state,
+    level_state,
+    press_button,
+    run,
+    start_game
+};
+
+fn tap_key(app: &mut App, key: KeyCode) {
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+    app.update();
+    app.world.resource_mut::<ButtonInput<KeyCode>>().release(key);
+    app.update();
+}
+
+fn tap_mouse(app: &mut App, button: MouseButton) {
+    app.world.resource_mut::<ButtonInput<MouseButton>>().press(button);
+    app.update();
+    app.world.resource_mut::<ButtonInput<MouseButton>>().release(button);
+    app.update();
+}
+
+// Shoots the start button like a player would
+fn shoot_start_button(app: &mut App) {
+    let mut button_query = app.world.query_filtered::<&GlobalTransform, With<StartButton>>();
+    let button = button_query.single(&app.world).translation();
+    look_at(&mut app.world, button);
+    app.update();
+    tap_mouse(app, MouseButton::Left);
+}
+
+// Kills every target of the level through the same event a hit sends
+fn kill_targets(app: &mut App) {
+    let mut target_query = app.world.query::<(Entity, &GlobalTransform, &TargetController)>();
+    let hits: Vec<TargetHitEvent> = target_query.iter(&app.world)
+        .map(|(target, transform, target_controller)| TargetHitEvent {
+            target,
+            position: transform.translation(),
+            damage: target_controller.health,
+            killed: true
+        })
+        .collect();
+    for hit in hits {
+        app.world.send_event(hit);
+    }
+    // the kill is applied, noticed and then moves to the next level
+    run(app, 3);
+}
+
+fn game_entity_count(app: &mut App) -> usize {
+    app.world.query_filtered::<Entity, With<GameEntity>>().iter(&app.world).count()
+}
+
+#[test]
+fn playing_through_every_level_wins() {
+    let mut app = headless_app();
+    start_game(&mut app);
+
+    let levels = [LevelState::Level1, LevelState::Level2, LevelState::Level3, LevelState::Level4, LevelState::Level5];
+    for level in levels {
+        assert_eq!(level_state(&app), level);
+        assert_eq!(game_state(&app), GameState::Start);
+
+        shoot_start_button(&mut app);
+        assert_eq!(game_state(&app), GameState::Playing, "start button was not hit on {:?}", level);
+
+        kill_targets(&mut app);
+    }
+
+    assert_eq!(game_state(&app), GameState::Won);
+    assert_eq!(level_state(&app), LevelState::NoLevel);
+}
+
+#[test]
+fn running_out_of_time_restarts_at_level_one() {
+    let mut app = headless_app();
+    start_game(&mut app);
+    shoot_start_button(&mut app);
+    kill_targets(&mut app);
+    assert_eq!(level_state(&app), LevelState::Level2);
+
+    shoot_start_button(&mut app);
+    assert_eq!(game_state(&app), GameState::Playing);
+    assert!(app.world.resource::<TimeController>().time_left.is_some());
+
+    // runs the whole level timer out, failing only lasts until the targets are cleared up so
+    // every tick is checked
+    let level_ticks = ((app.world.resource::<TimeController>().level_2_time + 1.0) / TICK) as usize;
+    let mut failed = false;
+    for _ in 0..level_ticks {
+        app.update();
+        failed |= level_state(&app) == LevelState::Failed;
+    }
+    assert!(failed, "never failed");
+    assert_eq!(level_state(&app), LevelState::Level1);
+    assert_eq!(game_state(&app), GameState::Start);
+    assert!(app.world.query::<&TargetController>().iter(&app.world).count() > 0);
+}
+
+#[test]
+fn resuming_from_the_pause_menu_returns_to_the_last_state() {
+    let mut app = headless_app();
+    start_game(&mut app);
+    shoot_start_button(&mut app);
+    assert_eq!(game_state(&app), GameState::Playing);
+
+    tap_key(&mut app, KeyCode::Escape);
+    assert_eq!(game_state(&app), GameState::PauseMenu);
+    assert_eq!(app.world.resource::<LastState>().state, Some(GameState::Playing));
+
+    press_button(&mut app, |action| matches!(action, MenuButtonAction::Resume));
+    assert_eq!(game_state(&app), GameState::Playing);
+}
+
+#[test]
+fn resuming_before_the_level_starts_returns_to_start() {
+    let mut app = headless_app();
+    start_game(&mut app);
+
+    tap_key(&mut app, KeyCode::Escape);
+    assert_eq!(game_state(&app), GameState::PauseMenu);
+
+    press_button(&mut app, |action| matches!(action, MenuButtonAction::Resume));
+    assert_eq!(game_state(&app), GameState::Start);
+}
+
+#[test]
+fn leaving_to_the_main_menu_despawns_every_game_entity() {
+    let mut app = headless_app();
+    start_game(&mut app);
+    shoot_start_button(&mut app);
+    assert!(game_entity_count(&mut app) > 0);
+
+    tap_key(&mut app, KeyCode::Escape);
+    assert_eq!(game_state(&app), GameState::PauseMenu);
+
+    press_button(&mut app, |action| matches!(action, MenuButtonAction::GoToMainMenu));
+    assert_eq!(game_state(&app), GameState::MainMenu);
+    assert_eq!(game_entity_count(&mut app), 0);
+}
+
+#[test]
+fn mouse_motion_turns_the_camera() {
+    let mut app = headless_app();
+    start_game(&mut app);
+
+    let mut camera_query = app.world.query::<&CameraController>();
+    let camera = camera_query.single(&app.world);
+    let (yaw, pitch) = (camera.yaw, camera.pitch);
+
+    app.world.send_event(MouseMotion { delta: Vec2::new(100.0, 50.0) });
+    app.update();
+
+    let camera = camera_query.single(&app.world);
+    assert!(camera.yaw < yaw, "moving the mouse right should turn right");
+    assert!(camera.pitch < pitch, "moving the mouse down should look down");
+}
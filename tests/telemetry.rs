@@ -0,0 +1,130 @@
+mod common;
+
+use bevy::prelude::*;
+use game::{
+    GameState,
+    LevelState,
+    headless::{
+        headless_app,
+        look_at
+    },
+    structs::{
+        CameraController,
+        HitKind,
+        ShotFiredEvent,
+        ShotHit,
+        StartButton,
+        TargetController,
+        TelemetryRecorder
+    },
+    telemetry::{
+        to_csv,
+        POSITION_INTERVAL,
+        to_json
+    }
+};
+use common::run;
+
+fn shoot_at(app: &mut App, point: Vec3) {
+    look_at(&mut app.world, point);
+    app.update();
+    app.world.resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Left);
+    run(app, 2);
+    app.world.resource_mut::<ButtonInput<MouseButton>>().release(MouseButton::Left);
+    run(app, 30);
+}
+
+#[test]
+fn every_shot_of_a_run_is_logged() {
+    let mut app = headless_app();
+    app.update();
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Start);
+    run(&mut app, 60);
+
+    let mut button_query = app.world.query_filtered::<&GlobalTransform, With<StartButton>>();
+    let button = button_query.single(&app.world).translation();
+    shoot_at(&mut app, button);
+    assert_eq!(app.world.resource::<State<GameState>>().get(), &GameState::Playing);
+
+    // none of the level 1 targets can be seen from the start room, so one is moved to where
+    // the start button was
+    let mut camera_query = app.world.query_filtered::<&GlobalTransform, With<CameraController>>();
+    let eye = camera_query.single(&app.world).translation();
+    let target = eye.lerp(button, 0.5);
+    let mut target_query = app.world.query::<(&TargetController, &mut Transform)>();
+    let (moved, mut transform) = target_query.iter_mut(&mut app.world).next().unwrap();
+    let id = moved.id;
+    transform.translation = target;
+    app.update();
+    shoot_at(&mut app, target);
+
+    let run_log = app.world.resource::<TelemetryRecorder>().current.clone().expect("the level should be logging shots");
+    assert_eq!(run_log.level, 1);
+    assert_eq!(run_log.shots.len(), 2);
+    // the player position is logged a few times a second for heatmaps
+    assert!(run_log.positions.len() as f32 >= run_log.time / POSITION_INTERVAL);
+    let (start_shot, target_shot) = (&run_log.shots[0], &run_log.shots[1]);
+    assert_eq!(start_shot.hit, HitKind::StartButton);
+    assert_eq!(target_shot.hit, HitKind::Target);
+    assert_eq!(target_shot.target_id, Some(id));
+    assert!(target_shot.time > start_shot.time);
+    let distance = target_shot.distance.unwrap();
+    assert!((distance - eye.distance(target)).abs() < 0.5, "hit at {} instead of {}", distance, eye.distance(target));
+    assert!((target_shot.direction.length() - 1.0).abs() < 1e-4);
+
+    // clearing the level finishes the run
+    let mut target_query = app.world.query_filtered::<Entity, With<TargetController>>();
+    let targets: Vec<Entity> = target_query.iter(&app.world).collect();
+    for target in targets {
+        app.world.entity_mut(target).despawn_recursive();
+    }
+    run(&mut app, 3);
+    assert_eq!(app.world.resource::<State<LevelState>>().get(), &LevelState::Level2);
+
+    let recorder = app.world.resource::<TelemetryRecorder>();
+    let finished = recorder.last.as_ref().expect("the level 1 run should be finished");
+    assert!(finished.cleared);
+    assert_eq!(finished.shots.len(), 2);
+
+    let csv = to_csv(finished);
+    assert_eq!(csv.lines().count(), 3);
+    assert!(csv.lines().all(|line| line.split(',').count() == 14));
+    let json: serde_json::Value = serde_json::from_str(&to_json(finished).unwrap()).unwrap();
+    assert_eq!(json["shots"][1]["target_id"], id);
+}
+
+#[test]
+fn moving_to_the_next_level_is_not_logged_as_velocity() {
+    let mut app = headless_app();
+    app.update();
+    app.world.resource_mut::<NextState<GameState>>().set(GameState::Start);
+    run(&mut app, 60);
+    let mut button_query = app.world.query_filtered::<&GlobalTransform, With<StartButton>>();
+    let button = button_query.single(&app.world).translation();
+    shoot_at(&mut app, button);
+
+    // a shot every tick while the player is moved to level 2
+    let mut target_query = app.world.query_filtered::<Entity, With<TargetController>>();
+    let targets: Vec<Entity> = target_query.iter(&app.world).collect();
+    for target in targets {
+        app.world.entity_mut(target).despawn_recursive();
+    }
+    for _ in 0..4 {
+        app.world.send_event(ShotFiredEvent {
+            origin: Vec3::ZERO,
+            end: Vec3::X,
+            camera_origin: Vec3::ZERO,
+            direction: Vec3::X,
+            hit: ShotHit::Nothing
+        });
+        app.update();
+    }
+    assert_eq!(app.world.resource::<State<LevelState>>().get(), &LevelState::Level2);
+
+    let recorder = app.world.resource::<TelemetryRecorder>();
+    let level2_shots = &recorder.current.as_ref().expect("level 2 should be logging shots").shots;
+    assert!(!level2_shots.is_empty());
+    for shot in recorder.last.as_ref().unwrap().shots.iter().chain(level2_shots) {
+        assert!(shot.velocity.length() < 20.0, "logged a velocity of {}", shot.velocity);
+    }
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use game::{
+    navigation::{
+        MapShape,
+        NavGrid
+    },
+    structs::{
+        LevelController,
+        PlayerController
+    },
+    validation::validate_level
+};
+
+fn setup() -> (MapShape, NavGrid, PlayerController) {
+    let map = MapShape::load().expect("map collision should load");
+    let player_controller = PlayerController::default();
+    let grid = NavGrid::build(&map, player_controller.spawn_point);
+    (map, grid, player_controller)
+}
+
+#[test]
+fn shipped_target_placement_is_valid() {
+    let (map, grid, player_controller) = setup();
+    let levels = LevelController::default();
+
+    // Only the placement is checked here, the time limits are a balance call
+    for positions in [&levels.level_1_pos, &levels.level_2_pos, &levels.level_3_pos, &levels.level_4_pos, &levels.level_5_pos] {
+        let report = validate_level(&map, &grid, player_controller.spawn_point, player_controller.speed, positions, f32::MAX);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert!(report.route_time.is_some());
+    }
+}
+
+#[test]
+fn broken_targets_are_flagged() {
+    let (map, grid, player_controller) = setup();
+    let spawn = player_controller.spawn_point;
+    let floor = map.floor_below(spawn, 3.0).expect("spawn should be above a floor");
+
+    let in_floor = Vec3::new(spawn.x, floor - 0.05, spawn.z);
+    let floating = spawn + Vec3::new(1.0, 6.0, 0.0);
+    let duplicate = spawn + Vec3::new(0.0, 0.5, -2.0);
+    let under_floor = Vec3::new(spawn.x, floor - 1.0, spawn.z);
+    let positions = [in_floor, floating, duplicate, duplicate + Vec3::X * 0.2, under_floor];
+
+    let report = validate_level(&map, &grid, spawn, player_controller.speed, &positions, f32::MAX);
+    let has = |text: &str| report.problems.iter().any(|problem| problem.contains(text));
+    assert!(has("target 1 at") && has("inside the map geometry"), "{:?}", report.problems);
+    assert!(has("floats more than"), "{:?}", report.problems);
+    assert!(has("targets 3 and 4 are only"), "{:?}", report.problems);
+    assert!(has("target 5 at") && has("can not be seen"), "{:?}", report.problems);
+    // an unseeable target means there is no route to time
+    assert!(report.route_time.is_none());
+
+    let report = validate_level(&map, &grid, spawn, player_controller.speed, &[duplicate], 0.1);
+    assert_eq!(report.problems.len(), 1, "{:?}", report.problems);
+    assert!(report.problems[0].contains("the limit is"));
+}
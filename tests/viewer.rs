@@ -0,0 +1,156 @@
+mod common;
+
+use bevy::{
+    input::{
+        keyboard::{
+            Key,
+            KeyboardInput,
+            NativeKey
+        },
+        ButtonState
+    },
+    prelude::*
+};
+use bevy_rapier3d::prelude::*;
+use game::{
+    GameState,
+    headless::headless_app,
+    viewer::{
+        kill_tick,
+        playing_start,
+        pose_at,
+        shot_results
+    },
+    structs::{
+        HitKind,
+        InputAction,
+        InputBinding,
+        InputBindings,
+        LevelController,
+        PlayerController,
+        Replay,
+        ReplayPose,
+        ReplayShot,
+        ReplayStart,
+        ReplayViewer,
+        Settings,
+        ShotMarker,
+        StartButton,
+        TargetController
+    }
+};
+use common::game_state;
+
+fn shot(end: Vec3, hit: HitKind, target_id: Option<usize>) -> Option<ReplayShot> {
+    Some(ReplayShot {
+        end,
+        hit,
+        target_id
+    })
+}
+
+// Ten ticks at the start button then a walk along x, with a hit on the first
+// level 1 target and a miss just above it
+fn walking_replay() -> Replay {
+    let target = LevelController::default().level_1_pos[0];
+    let mut poses: Vec<ReplayPose> = (0..70).map(|tick| ReplayPose {
+        translation: Vec3::new(tick as f32 * 0.05, -1.4, 16.5),
+        yaw: tick as f32 * 0.01,
+        pitch: 0.0,
+        shot: None
+    }).collect();
+    poses[5].shot = shot(Vec3::new(-9.0, -0.8, 15.9), HitKind::StartButton, None);
+    poses[20].shot = shot(target + Vec3::Y * 0.1, HitKind::Target, Some(0));
+    // close enough to the target to pass for a hit if it were guessed from the distance
+    poses[30].shot = shot(target + Vec3::Y * 0.15, HitKind::Map, None);
+
+    Replay {
+        level: 1,
+        cleared: false,
+        ticks: 60,
+        settings: Settings::default(),
+        start: ReplayStart::default(),
+        frames: Vec::new(),
+        poses
+    }
+}
+
+#[test]
+fn shots_are_split_into_hits_and_misses() {
+    let replay = walking_replay();
+
+    assert_eq!(playing_start(&replay), 10);
+    // the start button shot is left out
+    let shots: Vec<(usize, bool)> = shot_results(&replay).iter().map(|(tick, _, hit)| (*tick, *hit)).collect();
+    assert_eq!(shots, vec![(20, true), (30, false)]);
+    assert_eq!(kill_tick(&replay, 0), Some(20));
+    assert_eq!(kill_tick(&replay, 1), None);
+
+    let between = pose_at(&replay, 10.5).unwrap();
+    assert!((between.translation.x - 0.525).abs() < 1e-5);
+    assert_eq!(pose_at(&replay, 500.0).unwrap().translation, replay.poses[69].translation);
+}
+
+#[test]
+fn viewer_shows_the_replay_without_simulating() {
+    let mut app = headless_app();
+    app.insert_resource(ReplayViewer::new(walking_replay()));
+    for _ in 0..3 {
+        app.update();
+    }
+
+    assert_eq!(game_state(&app), GameState::Replay);
+    assert!(!app.world.resource::<RapierConfiguration>().physics_pipeline_active);
+    let mut start_query = app.world.query_filtered::<Entity, With<StartButton>>();
+    assert_eq!(start_query.iter(&app.world).count(), 0);
+    let mut marker_query = app.world.query::<&ShotMarker>();
+    assert_eq!(marker_query.iter(&app.world).count(), 2);
+
+    // nothing plays the game, the player just follows the poses
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyW);
+    app.world.resource_mut::<ReplayViewer>().tick = 25.0;
+    app.world.resource_mut::<ReplayViewer>().playing = false;
+    app.update();
+    let mut player_query = app.world.query_filtered::<&Transform, With<PlayerController>>();
+    assert_eq!(player_query.single(&app.world).translation, walking_replay().poses[25].translation);
+
+    let target = LevelController::default().level_1_pos[0];
+    let mut target_query = app.world.query_filtered::<(&Transform, &Visibility), With<TargetController>>();
+    for (transform, visibility) in target_query.iter(&app.world) {
+        let expected = if transform.translation == target { Visibility::Hidden } else { Visibility::Inherited };
+        assert_eq!(*visibility, expected);
+    }
+
+    // escape goes back to the menu and lets physics run again
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Escape);
+    app.update();
+    app.update();
+    assert_eq!(game_state(&app), GameState::MainMenu);
+    assert!(!app.world.contains_resource::<ReplayViewer>());
+    assert!(app.world.resource::<RapierConfiguration>().physics_pipeline_active);
+}
+
+#[test]
+fn viewer_controls_follow_the_bindings() {
+    let mut app = headless_app();
+    app.insert_resource(ReplayViewer::new(walking_replay()));
+    for _ in 0..3 {
+        app.update();
+    }
+    app.world.resource_mut::<InputBindings>().rebind(InputAction::SeekForward, InputBinding::Key(KeyCode::KeyL)).unwrap();
+    let mut viewer = app.world.resource_mut::<ReplayViewer>();
+    viewer.playing = false;
+    viewer.tick = 10.0;
+
+    // the old key does nothing anymore, the new one seeks to the end
+    for (key_code, tick) in [(KeyCode::ArrowRight, 10.0), (KeyCode::KeyL, 69.0)] {
+        app.world.send_event(KeyboardInput {
+            key_code,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state: ButtonState::Pressed,
+            window: Entity::PLACEHOLDER
+        });
+        app.update();
+        assert_eq!(app.world.resource::<ReplayViewer>().tick, tick);
+    }
+}